@@ -14,12 +14,92 @@ fn main() {
         .to_string();
     println!("{output_file}");
 
+    // In C, every enum variant shares one namespace, so `Ok` would be asking for a clash.
+    let mut c_config = cbindgen::Config::default();
+    c_config.enumeration.prefix_with_name = true;
+
     cbindgen::Builder::new()
-        .with_crate(crate_dir)
+        .with_config(c_config)
+        .with_crate(&crate_dir)
         .with_language(cbindgen::Language::C)
         .generate()
         .unwrap()
         .write_to_file(&output_file);
+
+    // The same for C++, except in a namespace and with `enum class`, so no prefixes are needed.
+    // `include/rover.hpp` wraps this header with RAII classes and exceptions.
+    let cxx_output_file = target_dir()
+        .join(format!("{package_name}.hpp"))
+        .display()
+        .to_string();
+    println!("{cxx_output_file}");
+
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_language(cbindgen::Language::Cxx)
+        .with_namespace(&package_name)
+        .generate()
+        .unwrap()
+        .write_to_file(&cxx_output_file);
+
+    write_pkg_config(&package_name);
+}
+
+/// Writes `<package>.pc` for linking against the cdylib, and `<package>-static.pc` for the
+/// staticlib, next to the headers. Point `PKG_CONFIG_PATH` at that directory to use them.
+fn write_pkg_config(package_name: &str) {
+    let version = env::var("CARGO_PKG_VERSION").unwrap();
+    let description = env::var("CARGO_PKG_DESCRIPTION").unwrap_or_default();
+    let include_dir = target_dir();
+    let lib_dir = profile_dir();
+
+    // The static library doesn't bring along the native libraries that std needs, so they have to
+    // be linked too. These are from `cargo rustc --crate-type staticlib -- --print
+    // native-static-libs`.
+    let native_static_libs = match env::var("CARGO_CFG_TARGET_OS").unwrap().as_str() {
+        "macos" => "-lSystem -lc -lm",
+        _ => "-lgcc_s -lutil -lrt -lpthread -lm -ldl -lc",
+    };
+
+    let pc = |name: &str, libs: &str| {
+        format!(
+            "includedir={include_dir}\n\
+             libdir={lib_dir}\n\
+             \n\
+             Name: {name}\n\
+             Description: {description}\n\
+             Version: {version}\n\
+             Cflags: -I${{includedir}}\n\
+             Libs: {libs}\n",
+            include_dir = include_dir.display(),
+            lib_dir = lib_dir.display(),
+        )
+    };
+    let dynamic = pc(package_name, &format!("-L${{libdir}} -l{package_name}"));
+    let r#static = pc(
+        &format!("{package_name}-static"),
+        &format!("${{libdir}}/lib{package_name}.a {native_static_libs}"),
+    );
+
+    for (file_name, contents) in [
+        (format!("{package_name}.pc"), dynamic),
+        (format!("{package_name}-static.pc"), r#static),
+    ] {
+        let path = include_dir.join(file_name);
+        println!("{}", path.display());
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+/// Find the directory for the current profile, e.g. `target/debug/`, which is where cargo puts the
+/// cdylib/staticlib. Cargo doesn't tell build scripts this, but `OUT_DIR` is always
+/// `<profile>/build/<package>-<hash>/out`.
+fn profile_dir() -> PathBuf {
+    PathBuf::from(env::var("OUT_DIR").unwrap())
+        .ancestors()
+        .nth(3)
+        .unwrap()
+        .to_path_buf()
 }
 
 /// Find the location of the `target/` directory. Note that this may be
@@ -0,0 +1,210 @@
+// Header-only C++ wrapper around the cbindgen-generated `c_rover.hpp`.
+//
+// Everything returned by `c_rover` is owned by a `std::unique_ptr` with the matching free function
+// as its deleter, so nothing leaks, and every error code is raised as an exception.
+#pragma once
+
+#include <cstdint>
+#include <memory>
+#include <stdexcept>
+#include <string>
+#include <utility>
+
+#include "c_rover.hpp"
+
+namespace rover {
+
+using c_rover::RoverErrorCode;
+
+/// The ABI version which this header was written against.
+constexpr uint32_t kAbiVersion = 1;
+
+struct StringDeleter {
+  void operator()(char *s) const { c_rover::extern_rover_free_string(s); }
+};
+/// A string allocated by `c_rover`.
+using UniqueString = std::unique_ptr<char, StringDeleter>;
+
+struct ResultDeleter {
+  void operator()(c_rover::RoverResult *result) const {
+    c_rover::extern_rover_result_free(result);
+  }
+};
+/// A result allocated by `c_rover`, including both of its strings.
+using UniqueResult = std::unique_ptr<c_rover::RoverResult, ResultDeleter>;
+
+struct CancellationTokenDeleter {
+  void operator()(c_rover::RoverCancellationToken *token) const {
+    c_rover::extern_rover_cancellation_token_free(token);
+  }
+};
+
+/// Every error from `c_rover`. Catch one of the subclasses to handle a kind of error, or this to
+/// handle all of them.
+class Error : public std::runtime_error {
+ public:
+  Error(RoverErrorCode code, const std::string &message)
+      : std::runtime_error(message), code_(code) {}
+
+  RoverErrorCode code() const noexcept { return code_; }
+
+ private:
+  RoverErrorCode code_;
+};
+
+/// The arguments could not be passed across the C interface (`CError`).
+class InterfaceError : public Error {
+  using Error::Error;
+};
+
+/// The mission was not in the right format, e.g. a malformed position or unknown instruction.
+class ParseError : public Error {
+  using Error::Error;
+};
+
+/// A rover started, or would have driven, off the plateau.
+class OutOfBoundsError : public Error {
+  using Error::Error;
+};
+
+/// The mission broke one of the limits set with `extern_rover_set_limits`, or, without them, one of
+/// the built-in limits on how many steps counts, groups and macros can expand to.
+class LimitError : public Error {
+  using Error::Error;
+};
+
+/// A rover tried to drive up or down a slope that was too steep for it.
+class TerrainError : public Error {
+  using Error::Error;
+};
+
+/// A rover's battery ran out before it finished its instructions.
+class OutOfEnergyError : public Error {
+  using Error::Error;
+};
+
+/// The mission was cancelled with `Simulation::cancel`.
+class CancelledError : public Error {
+  using Error::Error;
+};
+
+/// There is no way for a rover to get from where it is to where it was going.
+class UnreachableError : public Error {
+  using Error::Error;
+};
+
+/// Rovers in a lockstep mission were stuck waiting on each other.
+class DeadlockError : public Error {
+  using Error::Error;
+};
+
+/// A rover would have driven into an obstacle, or another rover, in a reactive program.
+class CrashError : public Error {
+  using Error::Error;
+};
+
+/// A rover's reactive program was still going after the most steps it was allowed.
+class StepLimitError : public Error {
+  using Error::Error;
+};
+
+/// Throws the exception matching `code`.
+[[noreturn]] inline void throw_error(RoverErrorCode code, const std::string &message) {
+  switch (code) {
+    case RoverErrorCode::NullPointerTopRight:
+    case RoverErrorCode::NullPointerInstruction:
+    case RoverErrorCode::InvalidUTF8TopRight:
+    case RoverErrorCode::InvalidUTF8Instruction:
+    case RoverErrorCode::NullPointerMissions:
+    case RoverErrorCode::InvalidUTF8Missions:
+    case RoverErrorCode::InvalidMissionsJson:
+    case RoverErrorCode::ThreadPool:
+      throw InterfaceError(code, message);
+    case RoverErrorCode::InvalidInput:
+    case RoverErrorCode::TokenCount:
+    case RoverErrorCode::InvalidInt:
+    case RoverErrorCode::DirectionLen:
+    case RoverErrorCode::InvalidDirection:
+    case RoverErrorCode::InvalidInstruction:
+    case RoverErrorCode::InvalidHeightmap:
+    case RoverErrorCode::InvalidSyntax:
+    case RoverErrorCode::InvalidMacroDefinition:
+    case RoverErrorCode::MacroError:
+      throw ParseError(code, message);
+    case RoverErrorCode::OutOfBoundsStartPosition:
+    case RoverErrorCode::OutOfBounds:
+      throw OutOfBoundsError(code, message);
+    case RoverErrorCode::TooManyRovers:
+    case RoverErrorCode::InstructionsTooLong:
+    case RoverErrorCode::PlateauTooLarge:
+    case RoverErrorCode::TooManySteps:
+      throw LimitError(code, message);
+    case RoverErrorCode::Cancelled:
+      throw CancelledError(code, message);
+    case RoverErrorCode::OutOfEnergy:
+      throw OutOfEnergyError(code, message);
+    case RoverErrorCode::TooSteep:
+      throw TerrainError(code, message);
+    case RoverErrorCode::Unreachable:
+      throw UnreachableError(code, message);
+    case RoverErrorCode::Deadlock:
+      throw DeadlockError(code, message);
+    case RoverErrorCode::Crash:
+      throw CrashError(code, message);
+    case RoverErrorCode::StepLimit:
+      throw StepLimitError(code, message);
+    default:
+      // A newer library with a code that this header doesn't know about.
+      throw Error(code, message);
+  }
+}
+
+/// The semantic version of the loaded library.
+inline std::string version() { return c_rover::extern_rover_version(); }
+
+/// Throws `std::runtime_error` if the loaded library has a different ABI version to this header.
+inline void check_abi() {
+  if (c_rover::extern_rover_abi_version() != kAbiVersion) {
+    throw std::runtime_error("c_rover " + version() + " has an incompatible ABI version");
+  }
+}
+
+/// Runs missions on a single plateau.
+///
+/// Owns a cancellation token, so a mission running on one thread can be cancelled from another
+/// with `cancel`. Once cancelled, every later `run` is cancelled too.
+class Simulation {
+ public:
+  explicit Simulation(std::string top_right)
+      : top_right_(std::move(top_right)),
+        token_(c_rover::extern_rover_cancellation_token_new()) {}
+
+  /// Returns the final positions of the rovers, one per line, or throws a subclass of `Error`.
+  std::string run(const std::string &instructions) const {
+    UniqueResult result(c_rover::extern_return_coordinates_result(
+        top_right_.c_str(), instructions.c_str(), token_.get()));
+    if (result->code != RoverErrorCode::Ok) {
+      throw_error(result->code, result->error);
+    }
+    return result->result;
+  }
+
+  /// The same as `run`, but returns the json string from `extern_return_coordinates`, and doesn't
+  /// throw, for callers that want to pass the json on as-is.
+  std::string run_json(const std::string &instructions) const {
+    UniqueString json(const_cast<char *>(
+        c_rover::extern_return_coordinates(top_right_.c_str(), instructions.c_str())));
+    return json.get();
+  }
+
+  /// Cancels the running mission, if any, and any later ones. Safe to call from any thread.
+  void cancel() const { c_rover::extern_rover_cancellation_token_cancel(token_.get()); }
+
+  const std::string &top_right() const noexcept { return top_right_; }
+
+ private:
+  std::string top_right_;
+  std::unique_ptr<c_rover::RoverCancellationToken, CancellationTokenDeleter> token_;
+};
+
+}  // namespace rover
@@ -0,0 +1,81 @@
+//! Functions for a caller to find out which build of `c_rover` it has loaded.
+//!
+//! On the go side, the library is picked by a path per platform, so there is nothing stopping an
+//! old `.so` being left behind in `target/`. These let the caller check before it makes any other
+//! call, and refuse to run against an incompatible build.
+
+/// The version of the C interface itself, as opposed to the crate version.
+///
+/// This is only bumped when an existing function changes signature/behaviour in a way that would
+/// break a caller. Adding a new function does not bump it; that is what the feature flags below
+/// are for.
+pub const ROVER_ABI_VERSION: u32 = 1;
+
+/// `extern_return_coordinates` is available. Always set, but it keeps the mask non-zero.
+pub const ROVER_FEATURE_COORDINATES: u64 = 1 << 0;
+/// `extern_return_coordinates_into_buf` is available.
+pub const ROVER_FEATURE_CALLER_BUFFER: u64 = 1 << 1;
+/// `extern_return_coordinates_batch` is available.
+pub const ROVER_FEATURE_BATCH: u64 = 1 << 2;
+/// `extern_rover_set_log_callback` and `extern_rover_set_log_level` are available.
+pub const ROVER_FEATURE_LOGGING: u64 = 1 << 3;
+/// `extern_rover_set_limits` is available.
+pub const ROVER_FEATURE_LIMITS: u64 = 1 << 4;
+/// `extern_return_coordinates_with_progress` and the `extern_rover_cancellation_token_*`
+/// functions are available.
+pub const ROVER_FEATURE_PROGRESS: u64 = 1 << 5;
+/// `extern_return_coordinates_result`, `extern_rover_result_free` and `extern_rover_free_string`
+/// are available.
+pub const ROVER_FEATURE_STRUCT_RESULT: u64 = 1 << 6;
+
+/// Every feature compiled into this build.
+const FEATURES: u64 = ROVER_FEATURE_COORDINATES
+    | ROVER_FEATURE_CALLER_BUFFER
+    | ROVER_FEATURE_BATCH
+    | ROVER_FEATURE_LOGGING
+    | ROVER_FEATURE_LIMITS
+    | ROVER_FEATURE_PROGRESS
+    | ROVER_FEATURE_STRUCT_RESULT;
+
+/// The crate's semantic version. This is NUL-terminated at compile time, so it is a static string
+/// and, unlike the other strings returned from this library, should *not* be freed by the caller.
+static VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// Returns the semantic version of this library, e.g. "0.0.2".
+///
+/// The returned pointer is to static memory, so it is valid for the lifetime of the loaded
+/// library and must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn extern_rover_version() -> *const libc::c_char {
+    VERSION.as_ptr().cast()
+}
+
+/// Returns `ROVER_ABI_VERSION`. A caller built against a different ABI version should refuse to
+/// run.
+#[unsafe(no_mangle)]
+pub extern "C" fn extern_rover_abi_version() -> u32 {
+    ROVER_ABI_VERSION
+}
+
+/// Returns a bitmask of the `ROVER_FEATURE_*` flags which are supported by this build.
+#[unsafe(no_mangle)]
+pub extern "C" fn extern_rover_features() -> u64 {
+    FEATURES
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn version_matches_crate() {
+        let version = unsafe { CStr::from_ptr(extern_rover_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn features_include_caller_buffer() {
+        assert_ne!(extern_rover_features() & ROVER_FEATURE_CALLER_BUFFER, 0);
+    }
+}
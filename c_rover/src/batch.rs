@@ -0,0 +1,235 @@
+//! Running many missions through a single FFI call.
+//!
+//! Calling `extern_return_coordinates` once per mission pays the FFI and json cost every time, and
+//! only ever uses the caller's thread. Here the caller sends every mission as one json array, they
+//! are run on a rayon thread pool, and the results come back as one json array in the same order.
+
+use crate::error::CError;
+use crate::{CoordinatesResult, into_c_string, run_options, string_from_c};
+use rayon::ThreadPool;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::num::NonZero;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A single mission within a batch: the same two strings as the arguments to
+/// `extern_return_coordinates`.
+#[derive(Serialize, Deserialize, Debug)]
+struct Mission {
+    top_right: String,
+    instructions: String,
+}
+
+/// The result of a whole batch.
+///
+/// `error` is only for failures of the batch itself, e.g. the json could not be parsed. A mission
+/// which fails does not fail the batch - its error is in its own entry of `results`, so one bad
+/// mission does not hide the results of all the others.
+#[derive(Serialize, Deserialize, Debug)]
+struct BatchResult {
+    /// One result per mission, in the same order as the input array.
+    results: Vec<CoordinatesResult>,
+    /// UTF-8 string with the error message, or empty on success.
+    error: String,
+}
+
+/// Runs every mission, in parallel unless `threads` is 1.
+///
+/// - `threads == 0` uses rayon's global pool, which has one thread per CPU.
+/// - `threads == 1` runs sequentially on the caller's thread, without starting a pool at all. This
+///   is the deterministic fallback, e.g. for debugging or for callers that do not want any
+///   threads being spun up behind their back.
+/// - Otherwise, a pool of exactly `threads` threads is used, up to one per CPU. It's kept for later
+///   batches of the same size, rather than starting the threads again for every call.
+///
+/// More threads than CPUs is an error: they couldn't run any faster, and each costs a stack.
+///
+/// Either way, `par_iter().collect()` keeps the results in input order.
+fn run_missions(missions: Vec<Mission>, threads: usize) -> Result<Vec<CoordinatesResult>, CError> {
+    let options = run_options();
+    let run = |mission: Mission| {
+        CoordinatesResult::from_mission(mission.top_right, mission.instructions, &options)
+    };
+    if threads == 1 {
+        return Ok(missions.into_iter().map(run).collect());
+    }
+    if threads == 0 {
+        return Ok(missions.into_par_iter().map(run).collect());
+    }
+    let pool = sized_pool(threads)?;
+    Ok(pool.install(|| missions.into_par_iter().map(run).collect()))
+}
+
+/// The most threads a batch can ask for: one per CPU.
+fn max_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZero::get)
+}
+
+/// The pool of exactly `threads` threads, started the first time a batch asks for it.
+///
+/// Only the last pool is kept, so a caller which keeps changing `threads` starts a new pool each
+/// time, but the threads of the old ones don't pile up.
+fn sized_pool(threads: usize) -> Result<Arc<ThreadPool>, CError> {
+    static POOL: Mutex<Option<Arc<ThreadPool>>> = Mutex::new(None);
+    let max = max_threads();
+    if threads > max {
+        return Err(CError::ThreadPool {
+            reason: format!("{threads} threads were asked for, but there are only {max} CPUs"),
+        });
+    }
+    let mut cached = POOL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(pool) = cached
+        .as_ref()
+        .filter(|pool| pool.current_num_threads() == threads)
+    {
+        return Ok(pool.clone());
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| CError::ThreadPool {
+            reason: e.to_string(),
+        })?;
+    let pool = Arc::new(pool);
+    *cached = Some(pool.clone());
+    Ok(pool)
+}
+
+/// Parses the json array of missions and runs them, returning the json-serialised `BatchResult`.
+fn batch_result_json(missions: Result<String, CError>, threads: usize) -> String {
+    let results = missions
+        .and_then(|missions| {
+            serde_json::from_str::<Vec<Mission>>(&missions).map_err(|e| {
+                CError::InvalidMissionsJson {
+                    reason: e.to_string(),
+                }
+            })
+        })
+        .and_then(|missions| run_missions(missions, threads));
+    let batch_result = match results {
+        Ok(results) => BatchResult {
+            results,
+            error: "".to_string(),
+        },
+        Err(e) => BatchResult {
+            results: Vec::new(),
+            error: e.into(),
+        },
+    };
+    serde_json::to_string(&batch_result).unwrap()
+}
+
+/// Runs a batch of missions in one call.
+///
+/// `missions` is a json array of objects, each with the two arguments to
+/// `extern_return_coordinates`, e.g. `[{"top_right": "5 5", "instructions": "1 2 N\nLMLMLMLMM"}]`.
+///
+/// Returns a json object `{"results": [...], "error": ""}`, where each entry of `results` has the
+/// same shape as the return value of `extern_return_coordinates`, in the same order as the input.
+///
+/// `threads` sets the size of the thread pool: 0 for one thread per CPU, or 1 to run sequentially
+/// on the calling thread. Asking for more threads than there are CPUs fails the batch.
+///
+/// # Safety
+///
+/// `missions` must either be null or point to a NUL-terminated C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_return_coordinates_batch(
+    missions: *const libc::c_char,
+    threads: u32,
+) -> *const libc::c_char {
+    let missions = unsafe {
+        string_from_c(
+            missions,
+            CError::NullPointerMissions,
+            CError::InvalidUTF8Missions,
+        )
+    };
+    into_c_string(batch_result_json(missions, threads as usize))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Enough missions that a pool with several threads will actually interleave them.
+    fn missions() -> Vec<Mission> {
+        (0..64)
+            .map(|i| Mission {
+                top_right: "5 5".to_string(),
+                // Every 8th mission drives off the plateau, so failures are interleaved too.
+                instructions: if i % 8 == 0 {
+                    "1 2 N\nMMMMMMMM".to_string()
+                } else {
+                    format!("{} 3 E\n{}", i % 5, "LR".repeat(i))
+                },
+            })
+            .collect()
+    }
+
+    /// The pool size the tests use, so that they share one pool rather than replacing each
+    /// other's. It's small enough for any machine.
+    fn threads() -> usize {
+        max_threads().min(3)
+    }
+
+    fn run(threads: usize) -> BatchResult {
+        let json = serde_json::to_string(&missions()).unwrap();
+        serde_json::from_str(&batch_result_json(Ok(json), threads)).unwrap()
+    }
+
+    #[test]
+    fn results_in_input_order() {
+        let batch = run(threads());
+        assert_eq!(batch.error, "");
+        assert_eq!(batch.results.len(), 64);
+        for (i, result) in batch.results.iter().enumerate() {
+            if i % 8 == 0 {
+                assert_eq!(result.result, "");
+                assert_ne!(result.error, "");
+            } else {
+                assert_eq!(result.result, format!("{} 3 E", i % 5));
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let sequential = serde_json::to_string(&run(1)).unwrap();
+        let parallel = serde_json::to_string(&run(0)).unwrap();
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn sized_pools_are_reused() {
+        let pool = sized_pool(threads()).unwrap();
+        assert_eq!(pool.current_num_threads(), threads());
+        assert!(Arc::ptr_eq(&pool, &sized_pool(threads()).unwrap()));
+        let parallel = serde_json::to_string(&run(threads())).unwrap();
+        assert_eq!(parallel, serde_json::to_string(&run(1)).unwrap());
+    }
+
+    #[test]
+    fn too_many_threads_fails_batch() {
+        let batch = run(max_threads() + 1);
+        assert!(batch.results.is_empty());
+        assert!(
+            batch
+                .error
+                .starts_with("could not start a thread pool for the batch")
+        );
+    }
+
+    #[test]
+    fn invalid_json_fails_batch() {
+        let batch: BatchResult =
+            serde_json::from_str(&batch_result_json(Ok("{}".to_string()), 1)).unwrap();
+        assert!(batch.results.is_empty());
+        assert!(
+            batch
+                .error
+                .starts_with("missions argument is not a valid json array")
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use rover::RoverError;
 use thiserror::Error;
 
 /// This is reserved exclusively for Errors that can come via the C interface.
@@ -13,6 +14,14 @@ pub enum CError {
     InvalidUTF8TopRight,
     #[error("instruction argument is not valid utf8")]
     InvalidUTF8Instruction,
+    #[error("Received null pointer missions argument")]
+    NullPointerMissions,
+    #[error("missions argument is not valid utf8")]
+    InvalidUTF8Missions,
+    #[error("missions argument is not a valid json array of missions: {reason}")]
+    InvalidMissionsJson { reason: String },
+    #[error("could not start a thread pool for the batch: {reason}")]
+    ThreadPool { reason: String },
 }
 
 impl From<CError> for String {
@@ -22,3 +31,91 @@ impl From<CError> for String {
         val.to_string()
     }
 }
+
+/// A number for every error, from either `CError` or `RoverError`, so that a caller can tell
+/// errors apart without matching on the message.
+///
+/// The numbers are part of the ABI, so a variant must never be renumbered. `CError`s are from 1,
+/// and `RoverError`s are from 100.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum RoverErrorCode {
+    #[default]
+    Ok = 0,
+    NullPointerTopRight = 1,
+    NullPointerInstruction = 2,
+    InvalidUTF8TopRight = 3,
+    InvalidUTF8Instruction = 4,
+    NullPointerMissions = 5,
+    InvalidUTF8Missions = 6,
+    InvalidMissionsJson = 7,
+    ThreadPool = 8,
+    InvalidInput = 100,
+    TokenCount = 101,
+    InvalidInt = 102,
+    DirectionLen = 103,
+    OutOfBoundsStartPosition = 104,
+    OutOfBounds = 105,
+    InvalidDirection = 106,
+    InvalidInstruction = 107,
+    TooManyRovers = 108,
+    InstructionsTooLong = 109,
+    PlateauTooLarge = 110,
+    TooManySteps = 111,
+    Cancelled = 112,
+    OutOfEnergy = 113,
+    InvalidHeightmap = 114,
+    TooSteep = 115,
+    Unreachable = 116,
+    Deadlock = 117,
+    InvalidSyntax = 118,
+    InvalidMacroDefinition = 119,
+    MacroError = 120,
+    Crash = 121,
+    StepLimit = 122,
+}
+
+impl From<&CError> for RoverErrorCode {
+    fn from(val: &CError) -> Self {
+        match val {
+            CError::NullPointerTopRight => RoverErrorCode::NullPointerTopRight,
+            CError::NullPointerInstruction => RoverErrorCode::NullPointerInstruction,
+            CError::InvalidUTF8TopRight => RoverErrorCode::InvalidUTF8TopRight,
+            CError::InvalidUTF8Instruction => RoverErrorCode::InvalidUTF8Instruction,
+            CError::NullPointerMissions => RoverErrorCode::NullPointerMissions,
+            CError::InvalidUTF8Missions => RoverErrorCode::InvalidUTF8Missions,
+            CError::InvalidMissionsJson { .. } => RoverErrorCode::InvalidMissionsJson,
+            CError::ThreadPool { .. } => RoverErrorCode::ThreadPool,
+        }
+    }
+}
+
+impl From<&RoverError> for RoverErrorCode {
+    fn from(val: &RoverError) -> Self {
+        match val {
+            RoverError::InvalidInput => RoverErrorCode::InvalidInput,
+            RoverError::TokenCount { .. } => RoverErrorCode::TokenCount,
+            RoverError::InvalidInt { .. } => RoverErrorCode::InvalidInt,
+            RoverError::DirectionLen { .. } => RoverErrorCode::DirectionLen,
+            RoverError::OutOfBoundsStartPosition { .. } => RoverErrorCode::OutOfBoundsStartPosition,
+            RoverError::OutOfBoundsError { .. } => RoverErrorCode::OutOfBounds,
+            RoverError::DirectionError { .. } => RoverErrorCode::InvalidDirection,
+            RoverError::InstructionError { .. } => RoverErrorCode::InvalidInstruction,
+            RoverError::TooManyRovers { .. } => RoverErrorCode::TooManyRovers,
+            RoverError::InstructionsTooLong { .. } => RoverErrorCode::InstructionsTooLong,
+            RoverError::PlateauTooLarge { .. } => RoverErrorCode::PlateauTooLarge,
+            RoverError::TooManySteps { .. } => RoverErrorCode::TooManySteps,
+            RoverError::Cancelled { .. } => RoverErrorCode::Cancelled,
+            RoverError::OutOfEnergy { .. } => RoverErrorCode::OutOfEnergy,
+            RoverError::InvalidHeightmap { .. } => RoverErrorCode::InvalidHeightmap,
+            RoverError::TooSteep { .. } => RoverErrorCode::TooSteep,
+            RoverError::Unreachable { .. } => RoverErrorCode::Unreachable,
+            RoverError::Deadlock { .. } => RoverErrorCode::Deadlock,
+            RoverError::InvalidSyntax { .. } => RoverErrorCode::InvalidSyntax,
+            RoverError::InvalidMacroDefinition { .. } => RoverErrorCode::InvalidMacroDefinition,
+            RoverError::MacroError { .. } => RoverErrorCode::MacroError,
+            RoverError::Crash { .. } => RoverErrorCode::Crash,
+            RoverError::StepLimit { .. } => RoverErrorCode::StepLimit,
+        }
+    }
+}
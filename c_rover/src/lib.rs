@@ -1,5 +1,12 @@
+mod abi;
+mod batch;
 mod error;
-use rover::return_coordinates;
+mod limits;
+mod logging;
+mod progress;
+mod result;
+use crate::error::{CError, RoverErrorCode};
+use rover::{RunOptions, return_coordinates_with_options};
 use serde::{Deserialize, Serialize};
 use std::ffi::{CStr, CString};
 
@@ -21,84 +28,189 @@ struct CoordinatesResult {
     result: String,
     /// UTF-8 string with the error message, or empty on success.
     error: String,
+    /// Not part of the json, which only has the message, but kept for the interfaces that return
+    /// a `RoverResult` struct instead.
+    #[serde(skip)]
+    code: RoverErrorCode,
 }
 
-/// # Safety
-///
-/// This function can be called from the C FFI via any language. Currently implemented is with go.
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn extern_return_coordinates(
-    top_right: *const libc::c_char,
-    instructions: *const libc::c_char,
-) -> *const libc::c_char {
-    fn c_err<S: Into<String>>(msg: S) -> *const libc::c_char {
-        let coordinate_result = CoordinatesResult {
+impl CoordinatesResult {
+    fn from_err(e: CError) -> Self {
+        CoordinatesResult {
             result: "".to_string(),
-            error: msg.into(),
-        };
-        CString::new(serde_json::to_string(&coordinate_result).unwrap())
-            .unwrap()
-            .into_raw()
+            code: RoverErrorCode::from(&e),
+            error: e.into(),
+        }
     }
 
-    if top_right.is_null() {
-        return c_err(error::CError::NullPointerTopRight);
+    /// Runs a single mission through the rover crate. The `options` should start from
+    /// `run_options`, so that the limits set by `extern_rover_set_limits` apply.
+    fn from_mission(top_right: String, instructions: String, options: &RunOptions) -> Self {
+        match return_coordinates_with_options(top_right, instructions, options) {
+            Ok(vec_of_lines) => CoordinatesResult {
+                result: vec_of_lines.join("\n"),
+                error: "".to_string(),
+                code: RoverErrorCode::Ok,
+            },
+            Err(e) => CoordinatesResult {
+                result: "".to_string(),
+                error: e.to_string(),
+                code: RoverErrorCode::from(&e),
+            },
+        }
     }
+}
 
-    if instructions.is_null() {
-        return c_err(error::CError::NullPointerInstruction);
+/// The options which every mission is run with, unless the entry point has more to add.
+fn run_options() -> RunOptions<'static> {
+    RunOptions {
+        limits: limits::current(),
+        ..Default::default()
     }
+}
 
-    let top_right = unsafe {
-        CStr::from_ptr(top_right)
+/// Reads a C string argument into an owned String, with `null_err`/`utf8_err` for the two ways
+/// that this can fail.
+///
+/// # Safety
+///
+/// The pointer must either be null or point to a NUL-terminated C string.
+unsafe fn string_from_c(
+    ptr: *const libc::c_char,
+    null_err: CError,
+    utf8_err: CError,
+) -> Result<String, CError> {
+    if ptr.is_null() {
+        return Err(null_err);
+    }
+    unsafe {
+        CStr::from_ptr(ptr)
             .to_str()
-            .map_err(|_| error::CError::InvalidUTF8TopRight)
+            .map_err(|_| utf8_err)
             .map(|s| s.to_owned())
+    }
+}
+
+/// Parses the two C string arguments and runs them through the rover crate. Shared by every
+/// extern entry point, so that the only difference between them is how the result is handed back
+/// to the caller.
+///
+/// # Safety
+///
+/// Both pointers must either be null or point to a NUL-terminated C string.
+unsafe fn coordinates_result(
+    top_right: *const libc::c_char,
+    instructions: *const libc::c_char,
+    options: &RunOptions,
+) -> CoordinatesResult {
+    let top_right = unsafe {
+        string_from_c(
+            top_right,
+            CError::NullPointerTopRight,
+            CError::InvalidUTF8TopRight,
+        )
     };
     let instructions = unsafe {
-        CStr::from_ptr(instructions)
-            .to_str()
-            .map_err(|_| error::CError::InvalidUTF8Instruction)
-            .map(|s| s.to_owned())
+        string_from_c(
+            instructions,
+            CError::NullPointerInstruction,
+            CError::InvalidUTF8Instruction,
+        )
     };
 
-    let (top_right, instructions) = match (top_right, instructions) {
-        (Ok(tr), Ok(ins)) => (tr, ins),
+    match (top_right, instructions) {
+        (Ok(tr), Ok(ins)) => CoordinatesResult::from_mission(tr, ins, options),
 
         // It's a little fiddly to match both at once
-        (Err(e), _) | (_, Err(e)) => return c_err(e),
-    };
+        (Err(e), _) | (_, Err(e)) => CoordinatesResult::from_err(e),
+    }
+}
 
-    match return_coordinates(top_right, instructions) {
-        Ok(vec_of_lines) => {
-            let output = vec_of_lines.join("\n");
-            // This would fail when there is an interior NUL, the possibility of which is pretty
-            // small, since I wrote the code.
-            // It would be possible to catch an error here too and adding another variation to the
-            // err enum.
-            CString::new(
-                serde_json::to_string(&CoordinatesResult {
-                    result: output,
-                    error: "".to_string(),
-                })
-                .unwrap(),
-            )
-            .unwrap()
-            .into_raw()
-        }
-        Err(e) => {
-            let msg = e.to_string();
-            CString::new(
-                serde_json::to_string(&CoordinatesResult {
-                    result: "".to_string(),
-                    error: msg,
-                })
-                .unwrap(),
-            )
-            .unwrap()
-            .into_raw()
-        }
+/// The same as `coordinates_result`, serialised to json.
+///
+/// # Safety
+///
+/// Both pointers must either be null or point to a NUL-terminated C string.
+unsafe fn coordinates_result_json(
+    top_right: *const libc::c_char,
+    instructions: *const libc::c_char,
+    options: &RunOptions,
+) -> String {
+    let coordinate_result = unsafe { coordinates_result(top_right, instructions, options) };
+    serde_json::to_string(&coordinate_result).unwrap()
+}
+
+/// Hands a json string back to the caller as a rust-allocated C string.
+fn into_c_string(json: String) -> *const libc::c_char {
+    // This would fail when there is an interior NUL, the possibility of which is pretty
+    // small, since I wrote the code (serde_json escapes any NUL inside the strings).
+    // It would be possible to catch an error here too and adding another variation to the
+    // err enum.
+    CString::new(json).unwrap().into_raw()
+}
+
+/// Frees a string returned by any of the functions in this library which return a json string.
+///
+/// # Safety
+///
+/// `s` must either be null (which does nothing), or a string returned by this library which
+/// has not already been freed. The static string from `extern_rover_version` must not be passed
+/// here.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_rover_free_string(s: *mut libc::c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// # Safety
+///
+/// This function can be called from the C FFI via any language. Currently implemented is with go.
+/// The returned string is allocated by rust, and can be freed with `extern_rover_free_string`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_return_coordinates(
+    top_right: *const libc::c_char,
+    instructions: *const libc::c_char,
+) -> *const libc::c_char {
+    into_c_string(unsafe { coordinates_result_json(top_right, instructions, &run_options()) })
+}
+
+/// The same as `extern_return_coordinates`, except that the json result is written into a buffer
+/// owned by the caller, rather than into memory allocated by rust. This means there is no
+/// allocator crossing the boundary at all, which suits callers that manage their own memory pools.
+///
+/// Follows the `snprintf` convention:
+/// - The return value is the length of the full json result in bytes, *not* counting the NUL
+///   terminator.
+/// - At most `buf_len - 1` bytes are written, and the buffer is always NUL-terminated when
+///   `buf_len > 0`. So if the return value is `>= buf_len`, the output was truncated, and the
+///   caller can retry with a buffer of `return value + 1` bytes.
+/// - If `buf` is null, nothing is written, so a null buffer can be used to query the size needed.
+///
+/// Truncation is by byte, so a truncated buffer may end part way through a UTF-8 character.
+///
+/// # Safety
+///
+/// `top_right` and `instructions` have the same requirements as in `extern_return_coordinates`.
+/// `buf` must either be null or valid for writes of `buf_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_return_coordinates_into_buf(
+    top_right: *const libc::c_char,
+    instructions: *const libc::c_char,
+    buf: *mut libc::c_char,
+    buf_len: libc::size_t,
+) -> libc::size_t {
+    let json = unsafe { coordinates_result_json(top_right, instructions, &run_options()) };
+    let needed = json.len();
+    if buf.is_null() || buf_len == 0 {
+        return needed;
+    }
+    let written = needed.min(buf_len - 1);
+    unsafe {
+        std::ptr::copy_nonoverlapping(json.as_ptr(), buf.cast::<u8>(), written);
+        *buf.add(written) = 0;
     }
+    needed
 }
 
 #[cfg(test)]
@@ -118,6 +230,83 @@ MMRMMRMRRM",
         )
         .unwrap()
         .into_raw();
-        extern_return_coordinates(top_right, instructions);
+        unsafe { extern_return_coordinates(top_right, instructions) };
+    }
+
+    const TOP_RIGHT: &str = "5 5";
+    const INSTRUCTIONS: &str = "1 2 N
+LMLMLMLMM
+3 3 E
+MMRMMRMRRM";
+    const EXPECTED: &str = r#"{"result":"1 3 N\n5 1 E","error":""}"#;
+
+    /// Calls the buffer variant with a buffer of `buf_len` bytes, returning the reported length
+    /// and the buffer contents up to the NUL terminator.
+    fn call_into_buf(buf_len: usize) -> (usize, String) {
+        let top_right = CString::new(TOP_RIGHT).unwrap();
+        let instructions = CString::new(INSTRUCTIONS).unwrap();
+        let mut buf = vec![b'x' as libc::c_char; buf_len];
+        let needed = unsafe {
+            extern_return_coordinates_into_buf(
+                top_right.as_ptr(),
+                instructions.as_ptr(),
+                buf.as_mut_ptr(),
+                buf_len,
+            )
+        };
+        let written = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        (needed, written.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn into_buf_exact_size() {
+        let (needed, written) = call_into_buf(EXPECTED.len() + 1);
+        assert_eq!(needed, EXPECTED.len());
+        assert_eq!(written, EXPECTED);
+    }
+
+    #[test]
+    fn into_buf_undersized() {
+        let (needed, written) = call_into_buf(10);
+        assert_eq!(needed, EXPECTED.len());
+        assert_eq!(written, EXPECTED[..9]);
+
+        // One byte short of room for the NUL still truncates.
+        let (needed, written) = call_into_buf(EXPECTED.len());
+        assert_eq!(needed, EXPECTED.len());
+        assert_eq!(written, EXPECTED[..EXPECTED.len() - 1]);
+    }
+
+    #[test]
+    fn into_buf_null_buffer() {
+        let top_right = CString::new(TOP_RIGHT).unwrap();
+        let instructions = CString::new(INSTRUCTIONS).unwrap();
+        let needed = unsafe {
+            extern_return_coordinates_into_buf(
+                top_right.as_ptr(),
+                instructions.as_ptr(),
+                std::ptr::null_mut(),
+                64,
+            )
+        };
+        assert_eq!(needed, EXPECTED.len());
+    }
+
+    #[test]
+    fn into_buf_reports_errors() {
+        let instructions = CString::new(INSTRUCTIONS).unwrap();
+        let mut buf = vec![0 as libc::c_char; 256];
+        let needed = unsafe {
+            extern_return_coordinates_into_buf(
+                std::ptr::null(),
+                instructions.as_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+            )
+        };
+        let written = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_str().unwrap();
+        assert_eq!(needed, written.len());
+        let parsed: CoordinatesResult = serde_json::from_str(written).unwrap();
+        assert_eq!(parsed.error, CError::NullPointerTopRight.to_string());
     }
 }
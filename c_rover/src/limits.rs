@@ -0,0 +1,65 @@
+//! Setting the `rover` crate's resource limits from the C side.
+//!
+//! The limits are process-wide, and apply to every mission run after they are set, including each
+//! mission within a batch.
+
+use rover::Limits;
+use std::sync::RwLock;
+
+static LIMITS: RwLock<Limits> = RwLock::new(Limits {
+    max_rovers: None,
+    max_instruction_len: None,
+    max_plateau_cells: None,
+    max_total_steps: None,
+});
+
+/// The limits that every mission is currently run with.
+pub(crate) fn current() -> Limits {
+    *LIMITS.read().unwrap()
+}
+
+/// C has no `Option`, so 0 stands in for unlimited. None of these limits are useful at 0 anyway.
+fn limit<T: TryFrom<u64>>(value: u64) -> Option<T> {
+    if value == 0 {
+        None
+    } else {
+        // Anything that doesn't fit in a usize is as good as unlimited.
+        T::try_from(value).ok()
+    }
+}
+
+/// Sets the resource limits for every following mission. A value of 0 means unlimited, which is
+/// the default for all of them.
+///
+/// - `max_rovers`: the number of rovers in one mission.
+/// - `max_instruction_len`: the number of instructions for a single rover.
+/// - `max_plateau_cells`: the number of cells on the plateau, e.g. "5 5" is 6 * 6 = 36 cells.
+/// - `max_total_steps`: the number of instructions across all of the rovers in one mission.
+///
+/// A mission which breaks a limit fails with an error, before any of it is simulated.
+#[unsafe(no_mangle)]
+pub extern "C" fn extern_rover_set_limits(
+    max_rovers: u64,
+    max_instruction_len: u64,
+    max_plateau_cells: u64,
+    max_total_steps: u64,
+) {
+    *LIMITS.write().unwrap() = Limits {
+        max_rovers: limit(max_rovers),
+        max_instruction_len: limit(max_instruction_len),
+        max_plateau_cells: limit(max_plateau_cells),
+        max_total_steps: limit(max_total_steps),
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zero_is_unlimited() {
+        assert_eq!(limit::<usize>(0), None);
+        assert_eq!(limit::<usize>(12), Some(12));
+        assert_eq!(limit::<u64>(u64::MAX), Some(u64::MAX));
+    }
+}
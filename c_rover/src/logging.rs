@@ -0,0 +1,174 @@
+//! Forwarding the `rover` crate's events to a C callback.
+//!
+//! Without this, the go side only ever sees the final error string. The `rover` crate emits its
+//! events through the `log` facade, and here we install a `log::Log` which hands each one to a
+//! callback registered by the caller, so that it can be routed into the caller's own logging.
+
+use std::ffi::CString;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Once, RwLock};
+
+use log::{LevelFilter, Log, Metadata, Record};
+use rover::LOG_TARGET;
+
+/// Logging is turned off.
+pub const ROVER_LOG_OFF: u32 = 0;
+pub const ROVER_LOG_ERROR: u32 = 1;
+pub const ROVER_LOG_WARN: u32 = 2;
+pub const ROVER_LOG_INFO: u32 = 3;
+pub const ROVER_LOG_DEBUG: u32 = 4;
+/// Includes an event for every single instruction, so is very noisy for long instructions.
+pub const ROVER_LOG_TRACE: u32 = 5;
+
+/// Receives a `ROVER_LOG_*` level, and a NUL-terminated UTF-8 message.
+///
+/// The message is only valid for the duration of the call, so it must be copied if it is kept.
+/// The message is of the form `event=<name> key=value ...`.
+///
+/// This is an `Option` so that it can be null (`None`) on the C side.
+pub type RoverLogCallback = Option<extern "C" fn(level: u32, message: *const libc::c_char)>;
+
+static CALLBACK: RwLock<RoverLogCallback> = RwLock::new(None);
+/// The level that the caller asked for, which is kept separately from `log::max_level`, because
+/// the max level is forced to `Off` whenever there isn't a callback.
+static LEVEL: AtomicUsize = AtomicUsize::new(ROVER_LOG_INFO as usize);
+static INSTALL: Once = Once::new();
+
+struct CallbackLogger;
+
+static LOGGER: CallbackLogger = CallbackLogger;
+
+impl Log for CallbackLogger {
+    /// Only the `rover` crate's events, and not anything else logged in the same process, e.g. by
+    /// a dependency, which the callback wouldn't know what to do with.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == LOG_TARGET && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Some(callback) = *CALLBACK.read().unwrap() else {
+            return;
+        };
+        // The events Debug-format any strings within them, which escapes NULs, so this should
+        // never fall back to the empty string.
+        let message = CString::new(record.args().to_string()).unwrap_or_default();
+        callback(record.level() as u32, message.as_ptr());
+    }
+
+    fn flush(&self) {}
+}
+
+fn level_filter(level: u32) -> Option<LevelFilter> {
+    match level {
+        ROVER_LOG_OFF => Some(LevelFilter::Off),
+        ROVER_LOG_ERROR => Some(LevelFilter::Error),
+        ROVER_LOG_WARN => Some(LevelFilter::Warn),
+        ROVER_LOG_INFO => Some(LevelFilter::Info),
+        ROVER_LOG_DEBUG => Some(LevelFilter::Debug),
+        ROVER_LOG_TRACE => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+/// Applies the requested level, unless there is no callback for the events to go to.
+fn apply_level() {
+    let filter = if CALLBACK.read().unwrap().is_some() {
+        level_filter(LEVEL.load(Ordering::Relaxed) as u32).unwrap_or(LevelFilter::Off)
+    } else {
+        LevelFilter::Off
+    };
+    log::set_max_level(filter);
+}
+
+/// Registers a callback to receive the rover events, replacing any previous callback. Passing
+/// null unregisters it.
+///
+/// The callback can be called from any thread (e.g. the thread pool of
+/// `extern_return_coordinates_batch`), and from several at once, so it must be thread-safe.
+///
+/// If something else in the process has already installed a rust logger, the callback will never
+/// be called, since there can only be one.
+#[unsafe(no_mangle)]
+pub extern "C" fn extern_rover_set_log_callback(callback: RoverLogCallback) {
+    INSTALL.call_once(|| {
+        // Only errors if another logger is installed, which there is nothing we can do about.
+        let _ = log::set_logger(&LOGGER);
+    });
+    *CALLBACK.write().unwrap() = callback;
+    apply_level();
+}
+
+/// Sets the most verbose `ROVER_LOG_*` level which will be passed to the callback. The default is
+/// `ROVER_LOG_INFO`.
+///
+/// Returns false, and leaves the level unchanged, if `level` is not one of the `ROVER_LOG_*`
+/// levels.
+#[unsafe(no_mangle)]
+pub extern "C" fn extern_rover_set_log_level(level: u32) -> bool {
+    if level_filter(level).is_none() {
+        return false;
+    }
+    LEVEL.store(level as usize, Ordering::Relaxed);
+    apply_level();
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extern_return_coordinates;
+    use std::ffi::CStr;
+    use std::sync::Mutex;
+
+    static RECEIVED: Mutex<Vec<(u32, String)>> = Mutex::new(Vec::new());
+
+    extern "C" fn collect(level: u32, message: *const libc::c_char) {
+        let message = unsafe { CStr::from_ptr(message) }.to_str().unwrap();
+        RECEIVED.lock().unwrap().push((level, message.to_string()));
+    }
+
+    #[test]
+    fn callback_receives_events() {
+        assert!(!extern_rover_set_log_level(6));
+        assert!(extern_rover_set_log_level(ROVER_LOG_DEBUG));
+        extern_rover_set_log_callback(Some(collect));
+
+        let top_right = CString::new("7 7").unwrap();
+        let instructions = CString::new("1 2 N\nMMXM").unwrap();
+        unsafe { extern_return_coordinates(top_right.as_ptr(), instructions.as_ptr()) };
+        log::info!(target: "not_rover", "event=from_elsewhere");
+        extern_rover_set_log_callback(None);
+
+        // Other tests may run at the same time and log too, so only look for our own events.
+        let received = RECEIVED.lock().unwrap();
+        assert!(received.contains(&(
+            ROVER_LOG_INFO,
+            r#"event=mission_parsed top_right="7 7" rovers=1"#.to_string()
+        )));
+        assert!(received.contains(&(
+            ROVER_LOG_DEBUG,
+            r#"event=rover_deployed rover=0 position="1 2 N""#.to_string()
+        )));
+        assert!(
+            received
+                .iter()
+                .any(|(level, message)| *level == ROVER_LOG_ERROR
+                    && message
+                        .starts_with("event=instruction_failed rover=0 step=2 instruction='X'"))
+        );
+        assert!(
+            !received
+                .iter()
+                .any(|(_, message)| message == "event=from_elsewhere")
+        );
+        // Trace is above the level that was set.
+        assert!(
+            !received
+                .iter()
+                .any(|(_, message)| message.starts_with("event=instruction_executed"))
+        );
+    }
+}
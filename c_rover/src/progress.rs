@@ -0,0 +1,196 @@
+//! Cancelling a mission from another thread, and reporting progress back to the caller.
+//!
+//! The cancellation token is an opaque handle owned by the caller, so that it can be cancelled
+//! (e.g. from another goroutine) while the mission runs on the calling thread.
+
+use crate::{coordinates_result_json, into_c_string, run_options};
+use rover::{CancellationToken, Progress, RunOptions};
+
+/// An opaque handle to a cancellation token. Create it with
+/// `extern_rover_cancellation_token_new`, and free it with
+/// `extern_rover_cancellation_token_free`.
+pub struct RoverCancellationToken(CancellationToken);
+
+/// A clone of the token behind the handle, or `None` if the handle is null.
+///
+/// # Safety
+///
+/// `token` must either be null or a token which has not been freed.
+pub(crate) unsafe fn token_from_ptr(
+    token: *const RoverCancellationToken,
+) -> Option<CancellationToken> {
+    unsafe { token.as_ref() }.map(|token| token.0.clone())
+}
+
+/// Receives the number of rovers which have completed, and the number of instructions executed
+/// so far, along with the `user_data` that was passed to
+/// `extern_return_coordinates_with_progress`.
+///
+/// Called on the thread that called `extern_return_coordinates_with_progress`, every
+/// `ROVER_PROGRESS_INTERVAL` instructions and after each rover finishes.
+pub type RoverProgressCallback = Option<
+    extern "C" fn(rovers_completed: u64, instructions_executed: u64, user_data: *mut libc::c_void),
+>;
+
+/// How many instructions are executed between each progress report/check for cancellation.
+///
+/// This is a literal rather than `rover::PROGRESS_INTERVAL`, so that cbindgen can put it in the
+/// header, and the assert below keeps the two in line.
+pub const ROVER_PROGRESS_INTERVAL: u64 = 1024;
+const _: () = assert!(ROVER_PROGRESS_INTERVAL == rover::PROGRESS_INTERVAL as u64);
+
+/// Creates a new cancellation token, which is not cancelled.
+#[unsafe(no_mangle)]
+pub extern "C" fn extern_rover_cancellation_token_new() -> *mut RoverCancellationToken {
+    Box::into_raw(Box::new(RoverCancellationToken(CancellationToken::new())))
+}
+
+/// Cancels any mission using this token. This is safe to call from any thread, including while
+/// the mission is running on another thread.
+///
+/// # Safety
+///
+/// `token` must either be null (which does nothing) or come from
+/// `extern_rover_cancellation_token_new`, and not yet have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_rover_cancellation_token_cancel(
+    token: *const RoverCancellationToken,
+) {
+    if let Some(token) = unsafe { token.as_ref() } {
+        token.0.cancel();
+    }
+}
+
+/// Frees a cancellation token.
+///
+/// # Safety
+///
+/// `token` must either be null (which does nothing) or come from
+/// `extern_rover_cancellation_token_new`, and not be in use by a running mission.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_rover_cancellation_token_free(token: *mut RoverCancellationToken) {
+    if !token.is_null() {
+        drop(unsafe { Box::from_raw(token) });
+    }
+}
+
+/// The `user_data` pointer, which rust can't know is safe to share between threads.
+///
+/// The progress callback is only ever called from the thread that called
+/// `extern_return_coordinates_with_progress`, so this never actually crosses threads.
+struct UserData(*mut libc::c_void);
+
+unsafe impl Sync for UserData {}
+
+impl UserData {
+    /// A method rather than using `.0` in the closure, because a closure would capture just the
+    /// raw pointer field, rather than the `Sync` wrapper.
+    fn get(&self) -> *mut libc::c_void {
+        self.0
+    }
+}
+
+/// The same as `extern_return_coordinates`, except that the mission can be cancelled with
+/// `cancellation`, and reports its progress to `on_progress`. Both are optional, and can be null.
+///
+/// A cancelled mission returns the usual json, with an error saying how far it got.
+///
+/// # Safety
+///
+/// `top_right` and `instructions` have the same requirements as in `extern_return_coordinates`.
+/// `cancellation` must either be null or a token which has not been freed. `user_data` is only
+/// passed back to `on_progress`, so it can be anything that the callback is expecting.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_return_coordinates_with_progress(
+    top_right: *const libc::c_char,
+    instructions: *const libc::c_char,
+    cancellation: *const RoverCancellationToken,
+    on_progress: RoverProgressCallback,
+    user_data: *mut libc::c_void,
+) -> *const libc::c_char {
+    let user_data = UserData(user_data);
+    let callback = on_progress.map(|on_progress| {
+        move |progress: Progress| {
+            on_progress(
+                progress.rovers_completed as u64,
+                progress.instructions_executed as u64,
+                user_data.get(),
+            )
+        }
+    });
+    let options = RunOptions {
+        cancellation: unsafe { token_from_ptr(cancellation) },
+        on_progress: callback
+            .as_ref()
+            .map(|callback| callback as &(dyn Fn(Progress) + Sync)),
+        ..run_options()
+    };
+    into_c_string(unsafe { coordinates_result_json(top_right, instructions, &options) })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CoordinatesResult;
+    use std::ffi::{CStr, CString};
+
+    fn long_mission() -> CString {
+        let spins = "LR".repeat(rover::PROGRESS_INTERVAL * 4);
+        CString::new(format!("1 2 N\n{spins}")).unwrap()
+    }
+
+    fn result(ptr: *const libc::c_char) -> CoordinatesResult {
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap();
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// Cancels the token in `user_data` once a couple of intervals have gone by, as if the caller
+    /// had cancelled it from another thread.
+    extern "C" fn cancel_after_two(
+        _rovers_completed: u64,
+        instructions_executed: u64,
+        user_data: *mut libc::c_void,
+    ) {
+        if instructions_executed >= ROVER_PROGRESS_INTERVAL * 2 {
+            unsafe { extern_rover_cancellation_token_cancel(user_data.cast()) };
+        }
+    }
+
+    #[test]
+    fn cancel_from_progress_callback() {
+        let top_right = CString::new("5 5").unwrap();
+        let instructions = long_mission();
+        let token = extern_rover_cancellation_token_new();
+        let res = result(unsafe {
+            extern_return_coordinates_with_progress(
+                top_right.as_ptr(),
+                instructions.as_ptr(),
+                token,
+                Some(cancel_after_two),
+                token.cast(),
+            )
+        });
+        unsafe { extern_rover_cancellation_token_free(token) };
+        assert_eq!(res.result, "");
+        assert_eq!(
+            res.error,
+            "Mission was cancelled after 0 rovers completed and 3072 instructions were executed"
+        );
+    }
+
+    #[test]
+    fn null_token_and_callback_runs_to_completion() {
+        let top_right = CString::new("5 5").unwrap();
+        let instructions = long_mission();
+        let res = result(unsafe {
+            extern_return_coordinates_with_progress(
+                top_right.as_ptr(),
+                instructions.as_ptr(),
+                std::ptr::null(),
+                None,
+                std::ptr::null_mut(),
+            )
+        });
+        assert_eq!(res.result, "1 2 N");
+    }
+}
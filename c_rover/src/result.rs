@@ -0,0 +1,123 @@
+//! A C struct result, as an alternative to the json string.
+//!
+//! The json is easy to use from go, but C and C++ callers would need a json parser just to find out
+//! whether there was an error. This gives the same result as a plain struct, along with a
+//! `RoverErrorCode` so that errors can be told apart without matching on the message.
+
+use crate::error::RoverErrorCode;
+use crate::progress::{RoverCancellationToken, token_from_ptr};
+use crate::{CoordinatesResult, coordinates_result, run_options};
+use rover::RunOptions;
+use std::ffi::CString;
+
+/// The result of a mission. Both strings are always non-null, and empty when not relevant.
+#[repr(C)]
+pub struct RoverResult {
+    /// `RoverErrorCode::Ok` on success.
+    pub code: RoverErrorCode,
+    /// The final positions of the rovers, one per line, or empty if there was an error.
+    pub result: *mut libc::c_char,
+    /// The error message, or empty on success.
+    pub error: *mut libc::c_char,
+}
+
+impl From<CoordinatesResult> for RoverResult {
+    fn from(val: CoordinatesResult) -> Self {
+        // Neither string can contain a NUL: the result is built by the rover crate, and the error
+        // messages only Debug-format any user input, which escapes NULs.
+        RoverResult {
+            code: val.code,
+            result: CString::new(val.result).unwrap_or_default().into_raw(),
+            error: CString::new(val.error).unwrap_or_default().into_raw(),
+        }
+    }
+}
+
+/// The same as `extern_return_coordinates`, except that the result is returned as a
+/// `RoverResult` struct, rather than as json. The mission can be cancelled with `cancellation`,
+/// which can be null.
+///
+/// The result must be freed with `extern_rover_result_free`.
+///
+/// # Safety
+///
+/// `top_right` and `instructions` have the same requirements as in `extern_return_coordinates`.
+/// `cancellation` must either be null or a token which has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_return_coordinates_result(
+    top_right: *const libc::c_char,
+    instructions: *const libc::c_char,
+    cancellation: *const RoverCancellationToken,
+) -> *mut RoverResult {
+    let options = RunOptions {
+        cancellation: unsafe { token_from_ptr(cancellation) },
+        ..run_options()
+    };
+    let result = unsafe { coordinates_result(top_right, instructions, &options) };
+    Box::into_raw(Box::new(RoverResult::from(result)))
+}
+
+/// Frees a result from `extern_return_coordinates_result`, including both of its strings.
+///
+/// # Safety
+///
+/// `result` must either be null (which does nothing), or come from
+/// `extern_return_coordinates_result` and not already have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn extern_rover_result_free(result: *mut RoverResult) {
+    if result.is_null() {
+        return;
+    }
+    let result = unsafe { Box::from_raw(result) };
+    unsafe {
+        drop(CString::from_raw(result.result));
+        drop(CString::from_raw(result.error));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ffi::CStr;
+
+    fn run(top_right: &str, instructions: &str) -> (RoverErrorCode, String, String) {
+        let top_right = CString::new(top_right).unwrap();
+        let instructions = CString::new(instructions).unwrap();
+        unsafe {
+            let result = extern_return_coordinates_result(
+                top_right.as_ptr(),
+                instructions.as_ptr(),
+                std::ptr::null(),
+            );
+            let out = (
+                (*result).code,
+                CStr::from_ptr((*result).result)
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+                CStr::from_ptr((*result).error)
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            );
+            extern_rover_result_free(result);
+            out
+        }
+    }
+
+    #[test]
+    fn success_has_ok_code() {
+        let (code, result, error) = run("5 5", "1 2 N\nLMLMLMLMM\n3 3 E\nMMRMMRMRRM");
+        assert_eq!(code, RoverErrorCode::Ok);
+        assert_eq!(result, "1 3 N\n5 1 E");
+        assert_eq!(error, "");
+    }
+
+    #[test]
+    fn rover_error_has_code() {
+        let (code, result, error) = run("5 5", "1 2 N\nMMMMMMMM");
+        assert_eq!(code, RoverErrorCode::OutOfBounds);
+        assert_eq!(result, "");
+        assert_eq!(error, "Instruction tried to send Rover too far \"N\"");
+    }
+}
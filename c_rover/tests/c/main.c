@@ -0,0 +1,30 @@
+/* Compiled and linked by `tests/pkg_config.rs`, against both the cdylib and the staticlib. */
+#include <stdio.h>
+#include <string.h>
+
+#include "c_rover.h"
+
+#define CHECK(cond)                                                        \
+  do {                                                                     \
+    if (!(cond)) {                                                         \
+      fprintf(stderr, "%s:%d: failed: %s\n", __FILE__, __LINE__, #cond);  \
+      return 1;                                                            \
+    }                                                                      \
+  } while (0)
+
+int main(void) {
+  CHECK(extern_rover_abi_version() == ROVER_ABI_VERSION);
+
+  struct RoverResult *result =
+      extern_return_coordinates_result("5 5", "1 2 N\nLMLMLMLMM\n3 3 E\nMMRMMRMRRM", NULL);
+  CHECK(result->code == RoverErrorCode_Ok);
+  CHECK(strcmp(result->result, "1 3 N\n5 1 E") == 0);
+  extern_rover_result_free(result);
+
+  result = extern_return_coordinates_result("5 5", "1 2 N\nMMMMMMMM", NULL);
+  CHECK(result->code == RoverErrorCode_OutOfBounds);
+  extern_rover_result_free(result);
+
+  printf("c_rover %s: all C checks passed\n", extern_rover_version());
+  return 0;
+}
@@ -0,0 +1,25 @@
+//! Helpers shared by the tests which compile a C/C++ program against `c_rover`.
+
+use std::env;
+use std::path::PathBuf;
+
+/// Where `build.rs` writes the generated headers and pkg-config files.
+pub fn header_dir() -> PathBuf {
+    if let Ok(target) = env::var("CARGO_TARGET_DIR") {
+        PathBuf::from(target)
+    } else {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target")
+    }
+}
+
+/// Where cargo puts the cdylib/staticlib: the test binary is in `<profile>/deps/`, and the
+/// libraries in `<profile>/`.
+pub fn lib_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().to_path_buf()
+}
+
+/// Where to put the compiled test programs.
+pub fn out_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+}
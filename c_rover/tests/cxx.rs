@@ -0,0 +1,44 @@
+//! Compiles `tests/cxx/main.cpp` against the `c_rover` cdylib, with the generated C++ header and
+//! the RAII wrapper in `include/rover.hpp`, then runs it.
+//!
+//! Needs a C++17 compiler, which is `c++` unless the `CXX` variable says otherwise.
+
+mod common;
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn cxx_wrapper() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let lib_dir = common::lib_dir();
+    let binary = common::out_dir().join("cxx_wrapper");
+    let cxx = env::var("CXX").unwrap_or_else(|_| "c++".to_string());
+
+    let status = Command::new(&cxx)
+        .arg("-std=c++17")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-I")
+        .arg(common::header_dir())
+        .arg(manifest_dir.join("tests/cxx/main.cpp"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lc_rover")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-o")
+        .arg(&binary)
+        .status()
+        .unwrap_or_else(|e| panic!("could not run the C++ compiler '{cxx}': {e}"));
+    assert!(status.success(), "compiling the C++ test failed");
+
+    let output = Command::new(&binary).output().unwrap();
+    assert!(
+        output.status.success(),
+        "C++ test failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
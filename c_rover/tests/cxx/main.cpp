@@ -0,0 +1,48 @@
+// Compiled and run by `tests/cxx.rs`, against the `c_rover` cdylib.
+#include <cstdio>
+#include <string>
+
+#include "rover.hpp"
+
+#define CHECK(cond)                                              \
+  do {                                                           \
+    if (!(cond)) {                                               \
+      std::fprintf(stderr, "%s:%d: failed: %s\n", __FILE__, __LINE__, #cond); \
+      return 1;                                                  \
+    }                                                            \
+  } while (0)
+
+int main() {
+  rover::check_abi();
+
+  rover::Simulation simulation("5 5");
+  CHECK(simulation.run("1 2 N\nLMLMLMLMM\n3 3 E\nMMRMMRMRRM") == "1 3 N\n5 1 E");
+  CHECK(simulation.run_json("1 2 N\nLMLMLMLMM") ==
+        R"({"result":"1 3 N","error":""})");
+
+  try {
+    simulation.run("1 2 N\nMMMMMMMM");
+    CHECK(false);
+  } catch (const rover::OutOfBoundsError &e) {
+    CHECK(e.code() == rover::RoverErrorCode::OutOfBounds);
+    CHECK(std::string(e.what()) == "Instruction tried to send Rover too far \"N\"");
+  }
+
+  try {
+    simulation.run("1 2 N\nMMXM");
+    CHECK(false);
+  } catch (const rover::ParseError &e) {
+    CHECK(e.code() == rover::RoverErrorCode::InvalidInstruction);
+  }
+
+  simulation.cancel();
+  try {
+    simulation.run("1 2 N\nLMLMLMLMM");
+    CHECK(false);
+  } catch (const rover::CancelledError &e) {
+    CHECK(e.code() == rover::RoverErrorCode::Cancelled);
+  }
+
+  std::printf("c_rover %s: all C++ checks passed\n", rover::version().c_str());
+  return 0;
+}
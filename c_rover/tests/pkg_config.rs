@@ -0,0 +1,78 @@
+//! Compiles `tests/c/main.c` using the generated pkg-config files, linking it once against the
+//! cdylib and once against the staticlib, then runs both.
+//!
+//! Needs a C compiler, which is `cc` unless the `CC` variable says otherwise, and `pkg-config`.
+
+mod common;
+
+use std::env;
+use std::process::Command;
+
+/// The output of `pkg-config <args> <package>`, split into arguments for the compiler.
+fn pkg_config(package: &str, args: &[&str]) -> Vec<String> {
+    let output = Command::new("pkg-config")
+        .env("PKG_CONFIG_PATH", common::header_dir())
+        .args(args)
+        .arg(package)
+        .output()
+        .unwrap_or_else(|e| panic!("could not run pkg-config: {e}"));
+    assert!(
+        output.status.success(),
+        "pkg-config failed for {package}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Compiles and runs the C program, linked with the flags from the `package` pkg-config file.
+fn compile_and_run(package: &str, extra_args: &[String]) {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let binary = common::out_dir().join(package);
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let status = Command::new(&cc)
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg(format!("{manifest_dir}/tests/c/main.c"))
+        .args(pkg_config(package, &["--cflags", "--libs"]))
+        .args(extra_args)
+        .arg("-o")
+        .arg(&binary)
+        .status()
+        .unwrap_or_else(|e| panic!("could not run the C compiler '{cc}': {e}"));
+    assert!(
+        status.success(),
+        "compiling the C test against {package} failed"
+    );
+
+    let output = Command::new(&binary).output().unwrap();
+    assert!(
+        output.status.success(),
+        "C test against {package} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn link_cdylib() {
+    // pkg-config doesn't set an rpath, so the test binary has to be told where the cdylib is.
+    let rpath = format!("-Wl,-rpath,{}", common::lib_dir().display());
+    compile_and_run("c_rover", &[rpath]);
+}
+
+#[test]
+fn link_staticlib() {
+    compile_and_run("c_rover-static", &[]);
+
+    // Make sure that it really is statically linked, rather than finding the cdylib.
+    let binary = common::out_dir().join("c_rover-static");
+    let output = Command::new("ldd").arg(&binary).output();
+    if let Ok(output) = output {
+        let libs = String::from_utf8_lossy(&output.stdout);
+        assert!(!libs.contains("libc_rover"), "linked dynamically: {libs}");
+    }
+}
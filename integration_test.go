@@ -74,3 +74,23 @@ MMMMMMMMMMMMMMMM`
 
 	t.Logf("final coordinates:\n%s", got)
 }
+
+func Test_CheckRoverABI_Integration(t *testing.T) {
+	libPath, err := resolveLibPath()
+	if err != nil {
+		t.Fatalf("Failed to resolve name to load the library. : %v", err)
+	}
+
+	rustlib, err := purego.Dlopen(libPath, purego.RTLD_NOW|purego.RTLD_GLOBAL)
+	if err != nil {
+		t.Fatalf("failed to load library. This could be because you have not built the rust FFI yet with `mask build`: %v", err)
+	}
+	defer purego.Dlclose(rustlib)
+
+	version, err := checkRoverABI(rustlib)
+	if err != nil {
+		t.Fatalf("library is not compatible: %v", err)
+	}
+
+	t.Logf("c_rover version: %s", version)
+}
@@ -11,6 +11,23 @@ import (
 
 type externFunc func(string, string) string
 
+// The ABI version of `c_rover` which this go code was written against.
+const roverABIVersion = 1
+
+// checkRoverABI refuses to run against a build of `c_rover` with a different ABI version.
+func checkRoverABI(rustlib uintptr) (string, error) {
+	var extern_rover_version func() string
+	var extern_rover_abi_version func() uint32
+	purego.RegisterLibFunc(&extern_rover_version, rustlib, "extern_rover_version")
+	purego.RegisterLibFunc(&extern_rover_abi_version, rustlib, "extern_rover_abi_version")
+
+	version := extern_rover_version()
+	if abi := extern_rover_abi_version(); abi != roverABIVersion {
+		return version, fmt.Errorf("c_rover %s has ABI version %d, expected %d", version, abi, roverABIVersion)
+	}
+	return version, nil
+}
+
 func getRoverCoordinates(call externFunc, topRight, instructions string) (string, error) {
 	type CoordinatesResult struct {
 		Result string `json:"result"`
@@ -53,6 +70,12 @@ func main() {
 	}
 	defer purego.Dlclose(rustlib)
 
+	version, err := checkRoverABI(rustlib)
+	if err != nil {
+		log.Fatalf("incompatible library: %v", err)
+	}
+	println("Loaded c_rover version:", version)
+
 	var extern_return_coordinates func(string, string) string
 	purego.RegisterLibFunc(&extern_return_coordinates, rustlib, "extern_return_coordinates")
 
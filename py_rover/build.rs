@@ -0,0 +1,15 @@
+fn main() {
+    pyo3_build_config::add_extension_module_link_args();
+
+    // Without `extension-module`, e.g. for `cargo test`, we link to libpython and embed the
+    // interpreter. A pyenv/conda python keeps libpython out of the linker's search path, so the
+    // test binaries need an rpath to find it.
+    let embedding = std::env::var_os("CARGO_FEATURE_EXTENSION_MODULE").is_none();
+    let unix = std::env::var("CARGO_CFG_TARGET_FAMILY").is_ok_and(|family| family == "unix");
+    if embedding
+        && unix
+        && let Some(lib_dir) = &pyo3_build_config::get().lib_dir
+    {
+        println!("cargo:rustc-link-arg=-Wl,-rpath,{lib_dir}");
+    }
+}
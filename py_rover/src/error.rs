@@ -0,0 +1,138 @@
+//! A python exception for every `RoverError` variant.
+//!
+//! They all subclass `RoverError`, so python callers can catch everything from the rover with one
+//! `except`, or pick out the errors they care about. The match in `into_py_err` is exhaustive, so
+//! adding a variant to `RoverError` won't compile until it has its own exception here.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    py_rover,
+    RoverError,
+    PyException,
+    "Base class of every error from the rover."
+);
+create_exception!(py_rover, InvalidInputError, RoverError);
+create_exception!(py_rover, TokenCountError, RoverError);
+create_exception!(py_rover, InvalidIntError, RoverError);
+create_exception!(py_rover, DirectionLenError, RoverError);
+create_exception!(py_rover, OutOfBoundsStartPositionError, RoverError);
+create_exception!(py_rover, OutOfBoundsError, RoverError);
+create_exception!(py_rover, DirectionError, RoverError);
+create_exception!(py_rover, InstructionError, RoverError);
+create_exception!(py_rover, TooManyRoversError, RoverError);
+create_exception!(py_rover, InstructionsTooLongError, RoverError);
+create_exception!(py_rover, PlateauTooLargeError, RoverError);
+create_exception!(py_rover, TooManyStepsError, RoverError);
+create_exception!(py_rover, CancelledError, RoverError);
+create_exception!(py_rover, OutOfEnergyError, RoverError);
+create_exception!(py_rover, InvalidHeightmapError, RoverError);
+create_exception!(py_rover, TooSteepError, RoverError);
+create_exception!(py_rover, UnreachableError, RoverError);
+create_exception!(py_rover, DeadlockError, RoverError);
+create_exception!(py_rover, InvalidSyntaxError, RoverError);
+create_exception!(py_rover, InvalidMacroDefinitionError, RoverError);
+create_exception!(py_rover, MacroError, RoverError);
+create_exception!(py_rover, CrashError, RoverError);
+create_exception!(py_rover, StepLimitError, RoverError);
+
+/// Converts a `rover::RoverError` into the matching python exception, with the same message.
+///
+/// A function rather than `From`, because neither `RoverError` nor `PyErr` belong to this crate.
+pub(crate) fn into_py_err(err: rover::RoverError) -> PyErr {
+    use rover::RoverError as E;
+    let message = err.to_string();
+    match err {
+        E::InvalidInput => InvalidInputError::new_err(message),
+        E::TokenCount { .. } => TokenCountError::new_err(message),
+        E::InvalidInt { .. } => InvalidIntError::new_err(message),
+        E::DirectionLen { .. } => DirectionLenError::new_err(message),
+        E::OutOfBoundsStartPosition { .. } => OutOfBoundsStartPositionError::new_err(message),
+        E::OutOfBoundsError { .. } => OutOfBoundsError::new_err(message),
+        E::DirectionError { .. } => DirectionError::new_err(message),
+        E::InstructionError { .. } => InstructionError::new_err(message),
+        E::TooManyRovers { .. } => TooManyRoversError::new_err(message),
+        E::InstructionsTooLong { .. } => InstructionsTooLongError::new_err(message),
+        E::PlateauTooLarge { .. } => PlateauTooLargeError::new_err(message),
+        E::TooManySteps { .. } => TooManyStepsError::new_err(message),
+        E::Cancelled { .. } => CancelledError::new_err(message),
+        E::OutOfEnergy { .. } => OutOfEnergyError::new_err(message),
+        E::InvalidHeightmap { .. } => InvalidHeightmapError::new_err(message),
+        E::TooSteep { .. } => TooSteepError::new_err(message),
+        E::Unreachable { .. } => UnreachableError::new_err(message),
+        E::Deadlock { .. } => DeadlockError::new_err(message),
+        E::InvalidSyntax { .. } => InvalidSyntaxError::new_err(message),
+        E::InvalidMacroDefinition { .. } => InvalidMacroDefinitionError::new_err(message),
+        E::MacroError { .. } => MacroError::new_err(message),
+        E::Crash { .. } => CrashError::new_err(message),
+        E::StepLimit { .. } => StepLimitError::new_err(message),
+    }
+}
+
+/// Adds all of the exceptions to the module.
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("RoverError", py.get_type::<RoverError>())?;
+    m.add("InvalidInputError", py.get_type::<InvalidInputError>())?;
+    m.add("TokenCountError", py.get_type::<TokenCountError>())?;
+    m.add("InvalidIntError", py.get_type::<InvalidIntError>())?;
+    m.add("DirectionLenError", py.get_type::<DirectionLenError>())?;
+    m.add(
+        "OutOfBoundsStartPositionError",
+        py.get_type::<OutOfBoundsStartPositionError>(),
+    )?;
+    m.add("OutOfBoundsError", py.get_type::<OutOfBoundsError>())?;
+    m.add("DirectionError", py.get_type::<DirectionError>())?;
+    m.add("InstructionError", py.get_type::<InstructionError>())?;
+    m.add("TooManyRoversError", py.get_type::<TooManyRoversError>())?;
+    m.add(
+        "InstructionsTooLongError",
+        py.get_type::<InstructionsTooLongError>(),
+    )?;
+    m.add(
+        "PlateauTooLargeError",
+        py.get_type::<PlateauTooLargeError>(),
+    )?;
+    m.add("TooManyStepsError", py.get_type::<TooManyStepsError>())?;
+    m.add("CancelledError", py.get_type::<CancelledError>())?;
+    m.add("OutOfEnergyError", py.get_type::<OutOfEnergyError>())?;
+    m.add(
+        "InvalidHeightmapError",
+        py.get_type::<InvalidHeightmapError>(),
+    )?;
+    m.add("TooSteepError", py.get_type::<TooSteepError>())?;
+    m.add("UnreachableError", py.get_type::<UnreachableError>())?;
+    m.add("DeadlockError", py.get_type::<DeadlockError>())?;
+    m.add("InvalidSyntaxError", py.get_type::<InvalidSyntaxError>())?;
+    m.add(
+        "InvalidMacroDefinitionError",
+        py.get_type::<InvalidMacroDefinitionError>(),
+    )?;
+    m.add("MacroError", py.get_type::<MacroError>())?;
+    m.add("CrashError", py.get_type::<CrashError>())?;
+    m.add("StepLimitError", py.get_type::<StepLimitError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_error_is_a_rover_error() {
+        Python::with_gil(|py| {
+            let err = into_py_err(rover::RoverError::OutOfBoundsError {
+                direction: "N".to_string(),
+            });
+            assert!(err.is_instance_of::<OutOfBoundsError>(py));
+            assert!(err.is_instance_of::<RoverError>(py));
+            assert!(!err.is_instance_of::<CancelledError>(py));
+            assert_eq!(
+                err.value(py).to_string(),
+                "Instruction tried to send Rover too far \"N\""
+            );
+        });
+    }
+}
@@ -0,0 +1,250 @@
+//! Python bindings for the `rover` crate, for callers who would otherwise shell out to the go
+//! binary.
+//!
+//! The classes are thin wrappers around the `rover` types: a `Plateau` is the top right
+//! `Coordinates`, a `Pose` is a `RoverPosition`, and a `Simulation` runs missions on a plateau.
+//! Errors are raised as the exceptions in `error`, one for each `RoverError` variant.
+
+mod error;
+
+use crate::error::into_py_err;
+use pyo3::prelude::*;
+use rover::{CancellationToken, Coordinates, Direction, RoverPosition, RunOptions};
+
+/// The plateau, given by its top right corner. The bottom left is always `(0, 0)`.
+#[pyclass(name = "Plateau", module = "py_rover", frozen, eq)]
+#[derive(Clone, PartialEq)]
+struct Plateau(Coordinates);
+
+#[pymethods]
+impl Plateau {
+    #[new]
+    fn new(latitude: u16, longitude: u16) -> Self {
+        Plateau(Coordinates {
+            latitude,
+            longitude,
+        })
+    }
+
+    /// Parses the first line of a mission, e.g. "5 5".
+    #[staticmethod]
+    fn parse(top_right: String) -> PyResult<Self> {
+        Coordinates::try_from(top_right)
+            .map(Plateau)
+            .map_err(into_py_err)
+    }
+
+    #[getter]
+    fn latitude(&self) -> u16 {
+        self.0.latitude
+    }
+
+    #[getter]
+    fn longitude(&self) -> u16 {
+        self.0.longitude
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Plateau({}, {})", self.0.latitude, self.0.longitude)
+    }
+}
+
+/// Where a rover is, and which way it is facing.
+#[pyclass(name = "Pose", module = "py_rover", frozen, eq)]
+#[derive(Clone, PartialEq)]
+struct Pose(RoverPosition);
+
+#[pymethods]
+impl Pose {
+    /// `facing` is one of "N", "S", "E" or "W".
+    #[new]
+    fn new(latitude: u16, longitude: u16, facing: char) -> PyResult<Self> {
+        let facing = Direction::try_from(facing).map_err(into_py_err)?;
+        Ok(Pose(RoverPosition {
+            current_location: Coordinates {
+                latitude,
+                longitude,
+            },
+            facing,
+        }))
+    }
+
+    /// Parses a rover's position line, e.g. "1 2 N".
+    #[staticmethod]
+    fn parse(position: String) -> PyResult<Self> {
+        RoverPosition::try_from(position)
+            .map(Pose)
+            .map_err(into_py_err)
+    }
+
+    #[getter]
+    fn latitude(&self) -> u16 {
+        self.0.current_location.latitude
+    }
+
+    #[getter]
+    fn longitude(&self) -> u16 {
+        self.0.current_location.longitude
+    }
+
+    #[getter]
+    fn facing(&self) -> String {
+        self.0.facing.into()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Pose({}, {}, {:?})",
+            self.latitude(),
+            self.longitude(),
+            self.facing()
+        )
+    }
+}
+
+/// Runs missions on a single plateau.
+///
+/// The GIL is released while a mission runs, so `cancel` can be called from another python
+/// thread. Once cancelled, every later `run` is cancelled too, the same as the C++ `Simulation`.
+#[pyclass(name = "Simulation", module = "py_rover", frozen)]
+struct Simulation {
+    plateau: Coordinates,
+    cancellation: CancellationToken,
+}
+
+#[pymethods]
+impl Simulation {
+    #[new]
+    fn new(plateau: Plateau) -> Self {
+        Simulation {
+            plateau: plateau.0,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    #[getter]
+    fn plateau(&self) -> Plateau {
+        Plateau(self.plateau)
+    }
+
+    /// Runs a mission in the exercise's format (without the plateau line): a position line and
+    /// an instructions line for each rover. Returns the final pose of each rover.
+    fn run(&self, py: Python<'_>, instructions: String) -> PyResult<Vec<Pose>> {
+        let options = RunOptions {
+            cancellation: Some(self.cancellation.clone()),
+            ..Default::default()
+        };
+        let top_right = self.plateau.to_string();
+        let positions = py
+            .allow_threads(|| {
+                rover::return_positions_with_options(top_right, instructions, &options)
+            })
+            .map_err(into_py_err)?;
+        Ok(positions.into_iter().map(Pose).collect())
+    }
+
+    /// Runs a single rover from `start`, returning where it ends up.
+    fn run_rover(&self, py: Python<'_>, start: Pose, instructions: &str) -> PyResult<Pose> {
+        // A newline would be read as the start of another rover.
+        if instructions.contains('\n') {
+            return Err(into_py_err(rover::RoverError::InvalidInput));
+        }
+        let mut poses = self.run(py, format!("{}\n{instructions}", start.0))?;
+        Ok(poses.remove(0))
+    }
+
+    /// Cancels the running mission, if any, and any later ones. Safe to call from any thread.
+    fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+}
+
+#[pymodule]
+fn py_rover(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Plateau>()?;
+    m.add_class::<Pose>()?;
+    m.add_class::<Simulation>()?;
+    error::register(m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+    use std::ffi::CStr;
+
+    /// Runs `code` with the module imported as `py_rover`, so that the tests go through the same
+    /// bindings as a python caller would.
+    fn run_python(code: &CStr) {
+        Python::with_gil(|py| {
+            // Globals rather than locals, so that the names are visible inside lambdas too.
+            let globals = PyDict::new(py);
+            globals
+                .set_item("py_rover", pyo3::wrap_pymodule!(py_rover)(py))
+                .unwrap();
+            if let Err(e) = py.run(code, Some(&globals), None) {
+                e.print(py);
+                panic!("python test failed: {e}");
+            }
+        });
+    }
+
+    #[test]
+    fn simulation_runs_example() {
+        run_python(
+            c"
+sim = py_rover.Simulation(py_rover.Plateau.parse('5 5'))
+poses = sim.run('1 2 N\\nLMLMLMLMM\\n3 3 E\\nMMRMMRMRRM')
+assert [str(p) for p in poses] == ['1 3 N', '5 1 E'], poses
+assert poses[0] == py_rover.Pose(1, 3, 'N')
+assert (poses[1].latitude, poses[1].longitude, poses[1].facing) == (5, 1, 'E')
+assert repr(poses[1]) == 'Pose(5, 1, \"E\")', repr(poses[1])
+assert sim.run_rover(py_rover.Pose.parse('1 2 N'), 'LMLMLMLMM') == py_rover.Pose(1, 3, 'N')
+assert sim.plateau == py_rover.Plateau(5, 5)
+",
+        );
+    }
+
+    #[test]
+    fn errors_are_raised_as_exceptions() {
+        run_python(
+            c"
+sim = py_rover.Simulation(py_rover.Plateau(5, 5))
+try:
+    sim.run('1 2 N\\nMMMMMMMM')
+    assert False
+except py_rover.OutOfBoundsError as e:
+    assert isinstance(e, py_rover.RoverError)
+    assert str(e) == 'Instruction tried to send Rover too far \"N\"', str(e)
+
+for bad, exception in [
+    (lambda: py_rover.Pose(1, 2, 'X'), py_rover.DirectionError),
+    (lambda: py_rover.Pose.parse('13N'), py_rover.TokenCountError),
+    (lambda: py_rover.Plateau.parse('a 5'), py_rover.InvalidIntError),
+    (lambda: sim.run('1 2 N'), py_rover.InvalidInputError),
+    (lambda: sim.run('1 2 N\\nMMXM'), py_rover.InstructionError),
+]:
+    try:
+        bad()
+        assert False, exception
+    except exception:
+        pass
+
+sim.cancel()
+try:
+    sim.run('1 2 N\\nLMLMLMLMM')
+    assert False
+except py_rover.CancelledError:
+    pass
+",
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! The two ways `return_positions_with_options` runs a rover's bytecode, on programs of a few
+//! million steps, written with a count, against the loop they replaced. Run with
+//! `cargo bench -p rover --features bench`.
+//!
+//! `char_loop` decodes and carries out one character at a time, as `try_process_rover` did before
+//! there was bytecode, on the same steps written out in full, so it doesn't include expanding the
+//! count. `stepped` gives the rovers a battery which never runs out, which is enough to make every
+//! instruction be carried out one at a time, the same as with terrain, a visitor or `Trace`
+//! events. `in_one_go` has none of those, so runs of the same instruction are done in one go, and
+//! a path which stays on the plateau is skipped to its end. Both include expanding the counts and
+//! compiling the steps, which is the same work either way.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use rover::{
+    Coordinates, EnergyModel, RoverPosition, RunOptions, interpret_chars,
+    return_positions_with_options,
+};
+use std::hint::black_box;
+
+const STEPS: usize = 4_000_000;
+
+fn compare(c: &mut Criterion, name: &str, unit: &str) {
+    let top_right = Coordinates {
+        latitude: 9,
+        longitude: 9,
+    };
+    let start: RoverPosition = "0 0 N".to_string().try_into().unwrap();
+    let repeats = STEPS / unit.len();
+    let steps = unit.repeat(repeats);
+    let mission = format!("{start}\n({unit}){repeats}");
+    let stepped = RunOptions {
+        energy: Some(EnergyModel::default()),
+        ..Default::default()
+    };
+    let in_one_go = RunOptions::default();
+    let run = |options: &RunOptions| {
+        return_positions_with_options(top_right.to_string(), black_box(mission.clone()), options)
+    };
+    let expected = interpret_chars(top_right, start, &steps).map(|position| vec![position]);
+    assert_eq!(run(&stepped), expected);
+    assert_eq!(run(&in_one_go), expected);
+
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.bench_function("char_loop", |b| {
+        b.iter(|| interpret_chars(top_right, start, black_box(&steps)))
+    });
+    group.bench_function("stepped", |b| b.iter(|| run(&stepped)));
+    group.bench_function("in_one_go", |b| b.iter(|| run(&in_one_go)));
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    // Long runs: round and round a 6x6 square.
+    compare(c, "runs", "MMMMMRMMMMMRMMMMMRMMMMMR");
+    // No runs at all: round and round a single cell.
+    compare(c, "alternating", "MR");
+}
+
+criterion_group!(bytecode, benches);
+criterion_main!(bytecode);
@@ -0,0 +1,229 @@
+//! Instructions compiled to bytecode, and the VM which runs it.
+//!
+//! Each rover's instructions are decoded from characters once, before it moves, into a list of
+//! `Op`s, where a run of the same instruction is a single op with a count. Counts and macros make
+//! long runs common, e.g. `1000M`, and a run can often be done in one go: turning is the same
+//! every four turns, and the rover goes as far as the edge of the plateau along a row or column
+//! in one move. That's only when nothing needs to see each step along the way, i.e. without
+//! energy, terrain, a visitor or `Trace` events; otherwise the VM still steps through a run one
+//! instruction at a time, but without decoding any characters.
+//!
+//! The VM runs on `RoverInformation`, so the rover is the same whichever way it gets there.
+
+use crate::error::RoverError;
+#[cfg(any(test, feature = "bench"))]
+use crate::{Coordinates, RoverPosition};
+use crate::{Direction, Instruction, RoverInformation, Spin};
+
+/// An instruction, carried out `count` times in a row. A longer run is split over several ops,
+/// which keeps them small, as there can be one per step.
+pub(crate) struct Op {
+    pub(crate) instruction: Instruction,
+    pub(crate) count: u32,
+}
+
+/// One rover's instructions, compiled.
+pub(crate) struct Bytecode {
+    pub(crate) ops: Vec<Op>,
+    /// The step which isn't an instruction, if there is one, with the character there and the
+    /// error for it. Compiling stops there, because the rover never gets any further.
+    pub(crate) invalid: Option<(usize, char, RoverError)>,
+}
+
+impl Bytecode {
+    /// Compiles the expanded `steps`, only accepting `L`, `R` and `M` when `strict`.
+    pub(crate) fn compile(steps: &str, strict: bool) -> Bytecode {
+        let mut ops: Vec<Op> = Vec::new();
+        for (step, c) in steps.chars().enumerate() {
+            let allowed = !strict || matches!(c, 'L' | 'R' | 'M');
+            match Instruction::decode(c).filter(|_| allowed) {
+                Some(instruction) => match ops.last_mut() {
+                    Some(op) if op.instruction == instruction && op.count < u32::MAX => {
+                        op.count += 1
+                    }
+                    _ => ops.push(Op {
+                        instruction,
+                        count: 1,
+                    }),
+                },
+                None => {
+                    let error = RoverError::InstructionError { received: c };
+                    return Bytecode {
+                        ops,
+                        invalid: Some((step, c, error)),
+                    };
+                }
+            }
+        }
+        Bytecode { ops, invalid: None }
+    }
+}
+
+impl RoverInformation<'_> {
+    /// Carries out the `instruction` `count` times in one go. If one of them fails, the rover is
+    /// left where it got to, and this returns how many were done before it, with the error.
+    ///
+    /// Slopes aren't checked, so this is only for a rover without terrain.
+    pub(crate) fn repeat(
+        &mut self,
+        instruction: Instruction,
+        count: usize,
+    ) -> Result<(), (usize, RoverError)> {
+        debug_assert!(self.terrain.is_none());
+        let Some(heading) = self.heading_of(&instruction) else {
+            if let Instruction::Pivot(spin) = instruction {
+                let turns = match spin {
+                    Spin::Around => count % 2,
+                    Spin::Left | Spin::Right => count % 4,
+                };
+                for _ in 0..turns {
+                    self.spin(spin);
+                }
+            }
+            return Ok(());
+        };
+        let location = &mut self.position.current_location;
+        let top_right = self.top_right_location;
+        let room = match heading {
+            Direction::North => top_right.longitude - location.longitude,
+            Direction::South => location.longitude,
+            Direction::East => top_right.latitude - location.latitude,
+            Direction::West => location.latitude,
+        };
+        // Never more than `room`, which is a u16.
+        let moves = count.min(usize::from(room)) as u16;
+        match heading {
+            Direction::North => location.longitude += moves,
+            Direction::South => location.longitude -= moves,
+            Direction::East => location.latitude += moves,
+            Direction::West => location.latitude -= moves,
+        }
+        if usize::from(moves) < count {
+            let error = self
+                .location_towards(heading)
+                .expect_err("the rover is at the edge");
+            Err((usize::from(moves), error))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The loop that ran each rover's instructions before there was bytecode, decoding one character
+/// at a time, without any of the options. It's only kept to check the VM against, and as the
+/// baseline in the benchmark, which is why the `bench` feature exports it.
+#[cfg(any(test, feature = "bench"))]
+pub fn interpret_chars(
+    top_right: Coordinates,
+    position: RoverPosition,
+    steps: &str,
+) -> Result<RoverPosition, RoverError> {
+    let mut rover = RoverInformation::try_new(top_right, position, None)?;
+    for c in steps.chars() {
+        rover.try_instruction(Instruction::try_from(c)?)?;
+    }
+    Ok(rover.position)
+}
+
+/// Compiles the `steps`, and runs them on the VM, without any of the options, to compare with
+/// `interpret_chars`.
+#[cfg(test)]
+fn interpret_bytecode(
+    top_right: Coordinates,
+    position: RoverPosition,
+    steps: &str,
+) -> Result<RoverPosition, RoverError> {
+    let mut rover = RoverInformation::try_new(top_right, position, None)?;
+    let bytecode = Bytecode::compile(steps, false);
+    for op in &bytecode.ops {
+        rover
+            .repeat(op.instruction, op.count as usize)
+            .map_err(|(_, error)| error)?;
+    }
+    match bytecode.invalid {
+        Some((_, _, error)) => Err(error),
+        None => Ok(rover.position),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PROGRESS_INTERVAL, Progress, RunOptions, return_positions_with_options};
+
+    #[test]
+    fn runs_are_compiled_together() {
+        let bytecode = Bytecode::compile("MMMLLRMX", false);
+        let ops: Vec<_> = bytecode
+            .ops
+            .iter()
+            .map(|op| (op.instruction, op.count))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                (Instruction::Forward, 3),
+                (Instruction::Pivot(Spin::Left), 2),
+                (Instruction::Pivot(Spin::Right), 1),
+                (Instruction::Forward, 1),
+            ]
+        );
+        assert_eq!(
+            bytecode.invalid,
+            Some((7, 'X', RoverError::InstructionError { received: 'X' }))
+        );
+        assert!(Bytecode::compile("MB", true).invalid.is_some());
+    }
+
+    #[test]
+    fn vm_matches_the_char_loop() {
+        let top_right = Coordinates {
+            latitude: 7,
+            longitude: 5,
+        };
+        let start: RoverPosition = "1 2 N".to_string().try_into().unwrap();
+        for steps in [
+            "LMLMLMLMM",
+            "MMMRMMMMMMMUMMMMBBBBLLLLLLM",
+            "RRRRRRRMMMMMWWWUUUBB",
+            "MMMMMMMM",
+            "LLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLLM",
+            "BMBMBMRRBBBBBBBBBBBBBBBBBB",
+        ] {
+            let expected = interpret_chars(top_right, start, steps);
+            assert_eq!(interpret_bytecode(top_right, start, steps), expected);
+            let res = return_positions_with_options(
+                top_right.to_string(),
+                format!("{start}\n{steps}"),
+                &Default::default(),
+            )
+            .map(|positions| positions[0]);
+            assert_eq!(res, expected, "{steps}");
+        }
+    }
+
+    #[test]
+    fn runs_still_report_progress() {
+        let reports = std::sync::Mutex::new(Vec::new());
+        let on_progress = |progress: Progress| reports.lock().unwrap().push(progress);
+        let options = RunOptions {
+            on_progress: Some(&on_progress),
+            ..Default::default()
+        };
+        let spins = "L".repeat(PROGRESS_INTERVAL * 3 + 1);
+        let res =
+            return_positions_with_options("5 5".to_string(), format!("1 2 N\n{spins}"), &options);
+        assert_eq!(res, Ok(vec!["1 2 W".to_string().try_into().unwrap()]));
+        let executed: Vec<_> = reports
+            .into_inner()
+            .unwrap()
+            .iter()
+            .map(|progress| progress.instructions_executed)
+            .collect();
+        let interval = PROGRESS_INTERVAL;
+        assert_eq!(
+            executed,
+            vec![0, interval, interval * 2, interval * 3, interval * 3 + 1]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! What the rovers' cameras see along the way, and how much of the plateau that adds up to.
+
+use crate::error::RoverError;
+use crate::{Coordinates, Direction, RoverPosition, RunOptions, run_mission};
+use std::collections::BTreeSet;
+
+/// The shape of the cells a camera can see, relative to the way the rover is facing. Both include
+/// the cell the rover is on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Footprint {
+    /// A 90 degree cone in front of the rover: one cell either side at 1 cell ahead, two either
+    /// side at 2 cells ahead, and so on.
+    Cone,
+    /// Straight lines ahead, behind and to either side of the rover.
+    Cross,
+}
+
+/// A rover's camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    /// How many cells away the camera can see.
+    pub range: u16,
+    pub footprint: Footprint,
+}
+
+impl Camera {
+    /// The cells on the plateau which the camera sees from `position`.
+    ///
+    /// Every cell in the footprint is seen for now. This is where obstacles blocking the view
+    /// would be taken into account.
+    fn observed_from(
+        &self,
+        position: &RoverPosition,
+        top_right: Coordinates,
+    ) -> impl Iterator<Item = Coordinates> {
+        let range = i32::from(self.range);
+        // (ahead, to the right) offsets from the rover.
+        let offsets: Vec<(i32, i32)> = match self.footprint {
+            Footprint::Cone => (0..=range)
+                .flat_map(|ahead| (-ahead..=ahead).map(move |right| (ahead, right)))
+                .collect(),
+            Footprint::Cross => std::iter::once((0, 0))
+                .chain((1..=range).flat_map(|d| [(d, 0), (-d, 0), (0, d), (0, -d)]))
+                .collect(),
+        };
+        let (x, y) = (
+            i32::from(position.current_location.latitude),
+            i32::from(position.current_location.longitude),
+        );
+        let facing = position.facing;
+        offsets.into_iter().filter_map(move |(ahead, right)| {
+            let (dx, dy) = match facing {
+                Direction::North => (right, ahead),
+                Direction::East => (ahead, -right),
+                Direction::South => (-right, -ahead),
+                Direction::West => (-ahead, right),
+            };
+            let latitude = u16::try_from(x + dx).ok()?;
+            let longitude = u16::try_from(y + dy).ok()?;
+            (latitude <= top_right.latitude && longitude <= top_right.longitude).then_some(
+                Coordinates {
+                    latitude,
+                    longitude,
+                },
+            )
+        })
+    }
+}
+
+/// The result of `survey`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoverageReport {
+    /// The cells each rover's camera saw, in the same order as the rovers in the mission.
+    pub observed: Vec<BTreeSet<Coordinates>>,
+    /// The percentage of the plateau's cells which any of the cameras saw.
+    pub coverage_percent: f64,
+    /// The cells which none of the cameras saw, ordered by latitude, then longitude.
+    pub unobserved: Vec<Coordinates>,
+}
+
+/// Runs the mission, and reports what the rovers' cameras saw from every position they were in
+/// along the way, including where they were deployed.
+///
+/// This fails if the mission does, the same as `return_coordinates_with_options`.
+pub fn survey(
+    top_right: String,
+    instructions: String,
+    options: &RunOptions,
+    camera: &Camera,
+) -> Result<CoverageReport, RoverError> {
+    let plateau = Coordinates::try_from(top_right.clone())?;
+    let mut observed: Vec<BTreeSet<Coordinates>> = Vec::new();
+    run_mission(
+        top_right,
+        instructions,
+        options,
+        Some(&mut |rover, position, _| {
+            if observed.len() <= rover {
+                observed.resize_with(rover + 1, BTreeSet::new);
+            }
+            observed[rover].extend(camera.observed_from(position, plateau));
+        }),
+    )?;
+
+    let seen: BTreeSet<Coordinates> = observed.iter().flatten().copied().collect();
+    let unobserved: Vec<Coordinates> = (0..=plateau.latitude)
+        .flat_map(|latitude| {
+            (0..=plateau.longitude).map(move |longitude| Coordinates {
+                latitude,
+                longitude,
+            })
+        })
+        .filter(|cell| !seen.contains(cell))
+        .collect();
+    let cells = (plateau.latitude as f64 + 1.0) * (plateau.longitude as f64 + 1.0);
+    Ok(CoverageReport {
+        observed,
+        coverage_percent: 100.0 * seen.len() as f64 / cells,
+        unobserved,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::at;
+
+    fn observed(camera: Camera, position: &str) -> BTreeSet<Coordinates> {
+        let position: RoverPosition = position.to_string().try_into().unwrap();
+        camera.observed_from(&position, at(5, 5)).collect()
+    }
+
+    #[test]
+    fn footprints_turn_with_the_rover() {
+        let cone = Camera {
+            range: 1,
+            footprint: Footprint::Cone,
+        };
+        assert_eq!(
+            observed(cone, "2 2 N"),
+            BTreeSet::from([at(2, 2), at(1, 3), at(2, 3), at(3, 3)])
+        );
+        assert_eq!(
+            observed(cone, "2 2 E"),
+            BTreeSet::from([at(2, 2), at(3, 1), at(3, 2), at(3, 3)])
+        );
+        // Clipped to the plateau.
+        assert_eq!(observed(cone, "0 0 S"), BTreeSet::from([at(0, 0)]));
+
+        let cross = Camera {
+            range: 2,
+            footprint: Footprint::Cross,
+        };
+        assert_eq!(observed(cross, "0 0 N").len(), 5);
+    }
+
+    #[test]
+    fn report_covers_the_path() {
+        let camera = Camera {
+            range: 1,
+            footprint: Footprint::Cross,
+        };
+        // Up the middle column of a 3x3 plateau, seeing either side of it.
+        let report = survey(
+            "2 2".to_string(),
+            "1 0 N\nMM".to_string(),
+            &RunOptions::default(),
+            &camera,
+        )
+        .unwrap();
+        assert_eq!(report.observed.len(), 1);
+        assert_eq!(report.observed[0].len(), 9);
+        assert_eq!(report.coverage_percent, 100.0);
+        assert!(report.unobserved.is_empty());
+
+        // Two rovers, each seeing only their own cell, with the rest unobserved.
+        let camera = Camera {
+            range: 0,
+            footprint: Footprint::Cone,
+        };
+        let report = survey(
+            "1 1".to_string(),
+            "0 0 N\n\n1 1 S\nL".to_string(),
+            &RunOptions::default(),
+            &camera,
+        )
+        .unwrap();
+        assert_eq!(
+            report.observed,
+            vec![BTreeSet::from([at(0, 0)]), BTreeSet::from([at(1, 1)])]
+        );
+        assert_eq!(report.coverage_percent, 50.0);
+        assert_eq!(report.unobserved, vec![at(0, 1), at(1, 0)]);
+    }
+}
@@ -0,0 +1,268 @@
+//! Plans instructions for a squad of rovers which, between them, drive over every cell of the
+//! plateau, so that the cameras get a complete view.
+//!
+//! The plateau is split into the regions the obstacles leave connected, and the cells of each
+//! region are ordered in a boustrophedon ("ox-turning") pattern: up the first column, down the
+//! next, and so on. That order is cut into one contiguous run for each rover in the region, with
+//! the same number of cells in each run, so that the rovers' paths are roughly the same length.
+//! Each rover then drives from cell to cell of its run with the shortest-path planner, skipping any
+//! cells which have already been driven over on the way.
+
+use crate::error::RoverError;
+use crate::planner::{PlanCosts, search};
+use crate::{Coordinates, RoverInformation, RoverPosition};
+use std::collections::{HashSet, VecDeque};
+
+/// The result of `plan_coverage`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoveragePlan {
+    /// The instructions for each rover, in the same order as the start poses.
+    pub instructions: Vec<String>,
+    /// Cells which aren't obstacles, but which no rover can reach, e.g. because they are walled in
+    /// by obstacles. Ordered by latitude, then longitude.
+    pub unreachable: Vec<Coordinates>,
+}
+
+/// Plans instructions for the rovers at `starts`, which between them visit every cell of the
+/// plateau that they can reach without driving onto an obstacle.
+///
+/// Each rover's instructions are independent of the others, so the plan can be run as one mission,
+/// with each rover's start position followed by its instructions. A rover which starts on an
+/// obstacle can still drive off it, but no rover plans to drive onto one.
+pub fn plan_coverage(
+    top_right: Coordinates,
+    obstacles: &[Coordinates],
+    starts: &[RoverPosition],
+    costs: &PlanCosts,
+) -> Result<CoveragePlan, RoverError> {
+    for start in starts {
+        RoverInformation::try_new(top_right, *start, None)?;
+    }
+    let obstacles: HashSet<Coordinates> = obstacles.iter().copied().collect();
+    let mut instructions = vec![String::new(); starts.len()];
+    let mut covered = HashSet::new();
+
+    // The rovers in each connected region, and the cells of that region.
+    let mut regions: Vec<(Vec<usize>, HashSet<Coordinates>)> = Vec::new();
+    for (rover, start) in starts.iter().enumerate() {
+        let location = start.current_location;
+        match regions
+            .iter_mut()
+            .find(|(_, cells)| cells.contains(&location))
+        {
+            Some((rovers, _)) => rovers.push(rover),
+            None => regions.push((vec![rover], region(top_right, &obstacles, location))),
+        }
+    }
+
+    for (rovers, cells) in &regions {
+        // A rover which starts on an obstacle puts that cell in its region, but the others can't
+        // drive onto it, so it is left out of the runs.
+        let order: Vec<Coordinates> = boustrophedon(top_right)
+            .filter(|cell| cells.contains(cell) && !obstacles.contains(cell))
+            .collect();
+        for (rover, run) in assign_runs(&order, rovers, starts) {
+            let mut position = starts[rover];
+            covered.insert(position.current_location);
+            for cell in run {
+                if covered.contains(&cell) {
+                    continue;
+                }
+                let is_goal = |position: &RoverPosition| position.current_location == cell;
+                // Anything this rover can't get to is left for `unreachable`, rather than
+                // trusting that the region is the same from every rover in it.
+                let Some((path, end)) = search(top_right, &obstacles, position, is_goal, costs)
+                else {
+                    continue;
+                };
+                mark_covered(top_right, position, &path, &mut covered);
+                instructions[rover].push_str(&path);
+                position = end;
+            }
+        }
+    }
+
+    let mut unreachable: Vec<Coordinates> = boustrophedon(top_right)
+        .filter(|cell| !obstacles.contains(cell) && !covered.contains(cell))
+        .collect();
+    unreachable.sort_by_key(|cell| (cell.latitude, cell.longitude));
+    Ok(CoveragePlan {
+        instructions,
+        unreachable,
+    })
+}
+
+/// Every cell of the plateau, up the first column, down the second, and so on.
+fn boustrophedon(top_right: Coordinates) -> impl Iterator<Item = Coordinates> {
+    (0..=top_right.latitude).flat_map(move |latitude| {
+        let column = (0..=top_right.longitude).map(move |longitude| Coordinates {
+            latitude,
+            longitude,
+        });
+        let column: Vec<_> = if latitude % 2 == 0 {
+            column.collect()
+        } else {
+            column.rev().collect()
+        };
+        column
+    })
+}
+
+/// The cells a rover at `start` can reach without driving onto an obstacle.
+fn region(
+    top_right: Coordinates,
+    obstacles: &HashSet<Coordinates>,
+    start: Coordinates,
+) -> HashSet<Coordinates> {
+    let mut cells = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(cell) = queue.pop_front() {
+        let (x, y) = (cell.latitude, cell.longitude);
+        let neighbours = [
+            (x.checked_sub(1), Some(y)),
+            (
+                x.checked_add(1).filter(|x| *x <= top_right.latitude),
+                Some(y),
+            ),
+            (Some(x), y.checked_sub(1)),
+            (
+                Some(x),
+                y.checked_add(1).filter(|y| *y <= top_right.longitude),
+            ),
+        ];
+        for (latitude, longitude) in neighbours {
+            let (Some(latitude), Some(longitude)) = (latitude, longitude) else {
+                continue;
+            };
+            let next = Coordinates {
+                latitude,
+                longitude,
+            };
+            if !obstacles.contains(&next) && cells.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    cells
+}
+
+/// Cuts `order` into a run for each of the `rovers`, with the same number of cells in each (give
+/// or take one). Each run goes to whichever rover is left that starts closest to one end of it,
+/// and is reversed if that rover is closer to its last cell.
+fn assign_runs(
+    order: &[Coordinates],
+    rovers: &[usize],
+    starts: &[RoverPosition],
+) -> Vec<(usize, Vec<Coordinates>)> {
+    let distance = |a: Coordinates, b: Coordinates| {
+        a.latitude.abs_diff(b.latitude) as u32 + a.longitude.abs_diff(b.longitude) as u32
+    };
+    let mut unassigned = rovers.to_vec();
+    let mut runs = Vec::with_capacity(rovers.len());
+    let (size, extra) = (order.len() / rovers.len(), order.len() % rovers.len());
+    let mut rest = order;
+    for i in 0..rovers.len() {
+        let (run, remainder) = rest.split_at(size + usize::from(i < extra));
+        rest = remainder;
+        let (Some(first), Some(last)) = (run.first(), run.last()) else {
+            runs.push((unassigned.remove(0), Vec::new()));
+            continue;
+        };
+        let (index, reverse) = unassigned
+            .iter()
+            .enumerate()
+            .map(|(index, rover)| {
+                let start = starts[*rover].current_location;
+                let (to_first, to_last) = (distance(start, *first), distance(start, *last));
+                (to_first.min(to_last), index, to_last < to_first)
+            })
+            .min()
+            .map(|(_, index, reverse)| (index, reverse))
+            .expect("there is a rover left for every run");
+        let mut run = run.to_vec();
+        if reverse {
+            run.reverse();
+        }
+        runs.push((unassigned.remove(index), run));
+    }
+    runs
+}
+
+/// Adds every cell that the rover at `start` drives over with `path` to `covered`.
+fn mark_covered(
+    top_right: Coordinates,
+    start: RoverPosition,
+    path: &str,
+    covered: &mut HashSet<Coordinates>,
+) {
+    let mut rover = RoverInformation {
+        position: start,
+        top_right_location: top_right,
+        terrain: None,
+    };
+    for instruction in path.chars() {
+        let instruction = instruction.try_into().expect("plans are only L, R and M");
+        rover
+            .try_instruction(instruction)
+            .expect("plans stay on the plateau");
+        covered.insert(rover.position.current_location);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::return_coordinates;
+    use crate::test_util::{at, pose};
+
+    /// Runs the plan for each rover, and returns every cell that they drive over between them,
+    /// checking that the plan is valid for the executor on the way.
+    fn visited(top_right: Coordinates, starts: &[RoverPosition], plan: &CoveragePlan) -> usize {
+        let mut cells = HashSet::new();
+        for (start, instructions) in starts.iter().zip(&plan.instructions) {
+            let mission = format!("{start}\n{instructions}");
+            return_coordinates(top_right.to_string(), mission).unwrap();
+            cells.insert(start.current_location);
+            mark_covered(top_right, *start, instructions, &mut cells);
+        }
+        cells.len()
+    }
+
+    #[test]
+    fn one_rover_covers_the_plateau() {
+        let starts = [pose("0 0 N")];
+        let plan = plan_coverage(at(4, 4), &[], &starts, &PlanCosts::default()).unwrap();
+        assert_eq!(visited(at(4, 4), &starts, &plan), 25);
+        assert!(plan.unreachable.is_empty());
+        // A boustrophedon visits every cell once: 24 moves, and 2 turns at the end of each column.
+        assert_eq!(plan.instructions[0].matches('M').count(), 24);
+    }
+
+    #[test]
+    fn rovers_share_the_work() {
+        let starts = [pose("0 0 N"), pose("5 5 S")];
+        let plan = plan_coverage(at(5, 5), &[], &starts, &PlanCosts::default()).unwrap();
+        assert_eq!(visited(at(5, 5), &starts, &plan), 36);
+        let (a, b) = (plan.instructions[0].len(), plan.instructions[1].len());
+        assert!(a.abs_diff(b) <= 4, "unbalanced: {a} and {b}");
+    }
+
+    #[test]
+    fn reports_unreachable_cells() {
+        // (0, 4) is walled in, and the obstacles themselves don't count.
+        let obstacles = [at(0, 3), at(1, 4)];
+        let starts = [pose("2 2 N")];
+        let plan = plan_coverage(at(4, 4), &obstacles, &starts, &PlanCosts::default()).unwrap();
+        assert_eq!(plan.unreachable, vec![at(0, 4)]);
+        assert_eq!(visited(at(4, 4), &starts, &plan), 22);
+    }
+
+    #[test]
+    fn rovers_join_a_rover_which_starts_on_an_obstacle() {
+        // The first rover's region includes the obstacle it is on, which the second can't reach.
+        let starts = [pose("0 2 N"), pose("0 0 N")];
+        let plan = plan_coverage(at(4, 4), &[at(0, 2)], &starts, &PlanCosts::default()).unwrap();
+        assert!(plan.unreachable.is_empty());
+        assert_eq!(visited(at(4, 4), &starts, &plan), 25);
+    }
+}
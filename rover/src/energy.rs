@@ -0,0 +1,133 @@
+//! An optional battery for each rover, which instructions drain and charging stations refill.
+
+use crate::{Coordinates, Instruction, Spin};
+
+/// How much energy each rover has, and what each instruction costs. Every rover starts the
+/// mission with a full `budget`.
+///
+/// A rover which can't afford its next instruction stops where it is, and the mission fails with
+/// `RoverError::OutOfEnergy`, which includes the instructions it didn't get to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnergyModel {
+    /// The energy each rover starts with, which is also the most it can hold.
+    pub budget: u64,
+    /// The cost of `M` or `B` on the flat, or downhill.
+    pub move_cost: u64,
+    /// The extra cost of `M` or `B` for each unit of elevation climbed, when there is `Terrain`.
+    pub climb_cost: u64,
+    /// The cost of `L` or `R`. `U` costs twice this.
+    pub spin_cost: u64,
+    /// Cells which refill a rover's battery to the full `budget` whenever it finishes an
+    /// instruction on one, whether it moved there or just turned on the spot.
+    pub charging_stations: Vec<Coordinates>,
+}
+
+/// The energy left in one rover's battery.
+pub(crate) struct Battery<'a> {
+    model: &'a EnergyModel,
+    charge: u64,
+}
+
+impl<'a> Battery<'a> {
+    pub(crate) fn new(model: &'a EnergyModel) -> Self {
+        Battery {
+            model,
+            charge: model.budget,
+        }
+    }
+
+    /// Takes the cost of the `instruction` out of the battery, or returns false (leaving the
+    /// battery as it was) if there isn't enough left. `climb` is how far a move would go up.
+    pub(crate) fn try_spend(&mut self, instruction: &Instruction, climb: u64) -> bool {
+        let cost = match instruction {
+            Instruction::Forward | Instruction::Backward => self
+                .model
+                .move_cost
+                .saturating_add(climb.saturating_mul(self.model.climb_cost)),
+            Instruction::Pivot(Spin::Around) => self.model.spin_cost.saturating_mul(2),
+            Instruction::Pivot(_) => self.model.spin_cost,
+            Instruction::Wait => 0,
+        };
+        match self.charge.checked_sub(cost) {
+            Some(charge) => {
+                self.charge = charge;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Refills the battery if `location` is a charging station.
+    pub(crate) fn recharge_at(&mut self, location: Coordinates) {
+        if self.model.charging_stations.contains(&location) {
+            self.charge = self.model.budget;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnergyModel, RoverError, RunOptions, return_coordinates_with_options};
+
+    fn run(instructions: &str, energy: EnergyModel) -> Result<Vec<String>, RoverError> {
+        let options = RunOptions {
+            energy: Some(energy),
+            ..Default::default()
+        };
+        return_coordinates_with_options("5 5".to_string(), instructions.to_string(), &options)
+    }
+
+    #[test]
+    fn runs_out_with_instructions_left() {
+        let energy = EnergyModel {
+            budget: 5,
+            move_cost: 2,
+            spin_cost: 1,
+            ..Default::default()
+        };
+        // L costs 1, then M costs 2 twice, which leaves 0 for the last L.
+        let res = run("2 2 N\nLMMLM", energy);
+        assert_eq!(
+            res,
+            Err(RoverError::OutOfEnergy {
+                rover: 0,
+                position: "0 2 W".to_string(),
+                remaining: "LM".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn exact_budget_is_enough() {
+        let energy = EnergyModel {
+            budget: 9,
+            move_cost: 1,
+            spin_cost: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            run("1 2 N\nLMLMLMLMM", energy),
+            Ok(vec!["1 3 N".to_string()])
+        );
+    }
+
+    #[test]
+    fn charging_station_refills() {
+        let mut energy = EnergyModel {
+            budget: 3,
+            move_cost: 1,
+            spin_cost: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            run("0 0 N\nMMMMM", energy.clone()),
+            Err(RoverError::OutOfEnergy { .. })
+        ));
+
+        energy.charging_stations.push(crate::Coordinates {
+            latitude: 0,
+            longitude: 2,
+        });
+        assert_eq!(run("0 0 N\nMMMMM", energy), Ok(vec!["0 5 N".to_string()]));
+    }
+}
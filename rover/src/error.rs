@@ -37,7 +37,84 @@ pub enum RoverError {
     DirectionError { received: char },
 
     #[error(
-        "Input was not a valid instruction. Rover instructions can either be L, R, M. Input recevied was {received}"
+        "Input was not a valid instruction. Rover instructions can either be L, R, M, W, B, U. Input recevied was {received}"
     )]
     InstructionError { received: char },
+
+    #[error("Mission has {got} rovers, but the limit is {max}")]
+    TooManyRovers { max: usize, got: usize },
+
+    #[error("Instructions for rover {rover} are {got} long, but the limit is {max}")]
+    InstructionsTooLong {
+        rover: usize,
+        max: usize,
+        got: usize,
+    },
+
+    #[error("Plateau has {got} cells, but the limit is {max}")]
+    PlateauTooLarge { max: u64, got: u64 },
+
+    #[error("Mission has more than the limit of {max} instructions in total")]
+    TooManySteps { max: usize },
+
+    #[error(
+        "Mission was cancelled after {rovers_completed} rovers completed and {instructions_executed} instructions were executed"
+    )]
+    Cancelled {
+        rovers_completed: usize,
+        instructions_executed: usize,
+    },
+
+    #[error("Rover {rover} ran out of energy at {position}, with instructions {remaining:?} left")]
+    OutOfEnergy {
+        rover: usize,
+        position: String,
+        remaining: String,
+    },
+
+    #[error("Heightmap is invalid: {reason}")]
+    InvalidHeightmap { reason: String },
+
+    #[error(
+        "Slope from {from} to {to} has a gradient of {gradient}, but the rover can only manage {max}"
+    )]
+    TooSteep {
+        from: String,
+        to: String,
+        gradient: i64,
+        max: u64,
+    },
+
+    #[error("There is no way for a rover to get from {from} to {to}")]
+    Unreachable { from: String, to: String },
+
+    #[error("Rovers {rovers} have been stuck waiting on each other since tick {tick}")]
+    Deadlock { rovers: String, tick: usize },
+
+    #[error("Instructions for rover {rover} are invalid at offset {offset}: {reason}")]
+    InvalidSyntax {
+        rover: usize,
+        offset: usize,
+        reason: String,
+    },
+
+    #[error("Macro definition on line {line} is invalid: {reason}")]
+    InvalidMacroDefinition { line: usize, reason: String },
+
+    #[error(
+        "Instructions for rover {rover} call a macro at offset {offset}, but {name} (defined on line {line}) is invalid: {reason}"
+    )]
+    MacroError {
+        rover: usize,
+        offset: usize,
+        name: String,
+        line: usize,
+        reason: String,
+    },
+
+    #[error("Rover {rover} would crash into whatever is at {cell}")]
+    Crash { rover: usize, cell: String },
+
+    #[error("Rover {rover}'s program was still going after the limit of {max} steps")]
+    StepLimit { rover: usize, max: usize },
 }
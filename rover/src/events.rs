@@ -0,0 +1,185 @@
+//! Structured events emitted while processing a mission.
+//!
+//! These go out through the [`log`] facade, so the crate doesn't need to know who (if anyone) is
+//! listening. Without a logger installed, `log!` is just a check against the max level, so this
+//! costs next to nothing. `c_rover` installs a logger which forwards to a C callback.
+
+use log::Level;
+
+/// The `target` for every event, so that a logger can pick out the rover events from anything
+/// else logged in the same process.
+pub const LOG_TARGET: &str = "rover";
+
+/// Everything of note that can happen while processing a mission.
+///
+/// The `rover` field is the zero-based index of the rover within the mission, in input order.
+#[derive(Debug, PartialEq)]
+pub enum RoverEvent {
+    /// The plateau and the rover/instruction pairs have been split out of the input.
+    MissionParsed { top_right: String, rovers: usize },
+    /// A rover's start position has been parsed, and it is within the plateau.
+    RoverDeployed { rover: usize, position: String },
+    /// A single instruction has been carried out. Only at `Trace`, because there is one of these
+    /// per character of the instructions.
+    InstructionExecuted {
+        rover: usize,
+        step: usize,
+        /// The tick the instruction was finished by.
+        tick: usize,
+        instruction: char,
+        position: String,
+    },
+    /// An instruction could not be parsed or carried out, so the whole mission fails.
+    InstructionFailed {
+        rover: usize,
+        step: usize,
+        instruction: char,
+        /// Where the instruction is in the rover's instructions as written, before any counts or
+        /// groups were expanded.
+        offset: usize,
+        error: String,
+    },
+    /// A rover has finished all of its instructions.
+    RoverFinished { rover: usize, position: String },
+    /// Rovers running in lockstep got in each other's way, so the moves were blocked.
+    Conflict {
+        tick: usize,
+        kind: &'static str,
+        rovers: Vec<usize>,
+        cell: String,
+    },
+    /// A reactive program checked a condition, and took a branch. Only at `Trace`, because a loop
+    /// can check its condition many times.
+    BranchTaken {
+        rover: usize,
+        step: usize,
+        offset: usize,
+        condition: String,
+        branch: &'static str,
+    },
+    /// Rovers running in lockstep were found stuck waiting on each other.
+    Deadlock {
+        tick: usize,
+        kind: &'static str,
+        rovers: Vec<usize>,
+    },
+}
+
+impl RoverEvent {
+    /// The level that each event is logged at.
+    pub fn level(&self) -> Level {
+        match self {
+            RoverEvent::MissionParsed { .. } => Level::Info,
+            RoverEvent::RoverDeployed { .. } => Level::Debug,
+            RoverEvent::InstructionExecuted { .. } => Level::Trace,
+            RoverEvent::InstructionFailed { .. } => Level::Error,
+            RoverEvent::RoverFinished { .. } => Level::Debug,
+            RoverEvent::Conflict { .. } => Level::Warn,
+            RoverEvent::BranchTaken { .. } => Level::Trace,
+            RoverEvent::Deadlock { .. } => Level::Warn,
+        }
+    }
+}
+
+/// Each event is written as an `event=<name>` followed by `key=value` pairs, so that the message
+/// stays machine-readable for whoever receives it, without needing a richer type across the FFI.
+impl std::fmt::Display for RoverEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoverEvent::MissionParsed { top_right, rovers } => {
+                write!(
+                    f,
+                    "event=mission_parsed top_right={top_right:?} rovers={rovers}"
+                )
+            }
+            RoverEvent::RoverDeployed { rover, position } => {
+                write!(
+                    f,
+                    "event=rover_deployed rover={rover} position={position:?}"
+                )
+            }
+            RoverEvent::InstructionExecuted {
+                rover,
+                step,
+                tick,
+                instruction,
+                position,
+            } => write!(
+                f,
+                "event=instruction_executed rover={rover} step={step} tick={tick} instruction={instruction:?} position={position:?}"
+            ),
+            RoverEvent::InstructionFailed {
+                rover,
+                step,
+                instruction,
+                offset,
+                error,
+            } => write!(
+                f,
+                "event=instruction_failed rover={rover} step={step} instruction={instruction:?} offset={offset} error={error:?}"
+            ),
+            RoverEvent::RoverFinished { rover, position } => {
+                write!(
+                    f,
+                    "event=rover_finished rover={rover} position={position:?}"
+                )
+            }
+            RoverEvent::Conflict {
+                tick,
+                kind,
+                rovers,
+                cell,
+            } => write!(
+                f,
+                "event=conflict tick={tick} kind={kind} rovers={rovers:?} cell={cell:?}"
+            ),
+            RoverEvent::BranchTaken {
+                rover,
+                step,
+                offset,
+                condition,
+                branch,
+            } => write!(
+                f,
+                "event=branch_taken rover={rover} step={step} offset={offset} condition={condition:?} branch={branch}"
+            ),
+            RoverEvent::Deadlock { tick, kind, rovers } => {
+                write!(
+                    f,
+                    "event=deadlock tick={tick} kind={kind} rovers={rovers:?}"
+                )
+            }
+        }
+    }
+}
+
+/// Whether events at `level` would go anywhere. Only needed to skip building an event on the
+/// per-instruction hot path; everywhere else can just call `emit`.
+pub(crate) fn enabled(level: Level) -> bool {
+    log::log_enabled!(target: LOG_TARGET, level)
+}
+
+/// Emits an event through the `log` facade, at the event's own level.
+pub(crate) fn emit(event: RoverEvent) {
+    log::log!(target: LOG_TARGET, event.level(), "{event}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_is_key_value() {
+        let event = RoverEvent::InstructionFailed {
+            rover: 1,
+            step: 4,
+            instruction: 'M',
+            offset: 2,
+            error: "too far".to_string(),
+        };
+        assert_eq!(
+            event.to_string(),
+            r#"event=instruction_failed rover=1 step=4 instruction='M' offset=2 error="too far""#
+        );
+    }
+}
@@ -1,14 +1,59 @@
 use std::char;
 
+mod bytecode;
+mod camera;
+mod coverage;
+mod energy;
 mod error;
-
-use crate::error::RoverError;
-
-/// Coordinates for the Mars Rover's location
-#[derive(Copy, Clone, Debug, PartialEq)]
-struct Coordinates {
-    latitude: u16,
-    longitude: u16,
+mod events;
+mod limits;
+mod lockstep;
+mod planner;
+mod program;
+mod progress;
+mod reactive;
+mod squad;
+mod terrain;
+#[cfg(test)]
+mod test_util;
+mod timeline;
+mod transform;
+
+use crate::bytecode::Bytecode;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use crate::bytecode::interpret_chars;
+pub use crate::camera::{Camera, CoverageReport, Footprint, survey};
+pub use crate::coverage::{CoveragePlan, plan_coverage};
+use crate::energy::Battery;
+pub use crate::energy::EnergyModel;
+pub use crate::error::RoverError;
+pub use crate::events::LOG_TARGET;
+use crate::events::RoverEvent;
+pub use crate::limits::Limits;
+pub use crate::lockstep::{
+    Blocked, Conflict, ConflictKind, Deadlock, DeadlockKind, DeadlockPolicy, LockstepResult,
+    run_lockstep,
+};
+pub use crate::planner::{PlanCosts, plan_path};
+use crate::program::{DEFINITION, Macros, Program};
+pub use crate::program::{MAX_EXPANDED_STEPS, MAX_EXPANDED_TOTAL, MAX_NESTING};
+use crate::progress::Tracker;
+pub use crate::progress::{CancellationToken, PROGRESS_INTERVAL, Progress};
+pub use crate::reactive::{BranchTrace, Condition, ReactiveResult, run_reactive};
+pub use crate::squad::plan_squad;
+pub use crate::terrain::{Heightmap, Terrain};
+pub use crate::timeline::{Durations, TimelineEntry, return_timeline};
+pub use crate::transform::{NetTransform, Offset, net_transform};
+use log::Level;
+
+/// Coordinates for the Mars Rover's location, or for the top right corner of the plateau.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Coordinates {
+    /// The x coordinate, increasing to the East.
+    pub latitude: u16,
+    /// The y coordinate, increasing to the North.
+    pub longitude: u16,
 }
 impl TryFrom<String> for Coordinates {
     type Error = RoverError;
@@ -37,9 +82,16 @@ impl TryFrom<String> for Coordinates {
     }
 }
 
+/// The plateau format of the exercise, e.g. "5 5".
+impl std::fmt::Display for Coordinates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.latitude, self.longitude)
+    }
+}
+
 /// Represents each direction that the Rover can be facing
 #[derive(Copy, Clone, Debug, PartialEq)]
-enum Direction {
+pub enum Direction {
     North,
     South,
     East,
@@ -71,36 +123,86 @@ impl TryFrom<char> for Direction {
     }
 }
 
+impl Direction {
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
 /// Represents inputs for spinning the rover
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 enum Spin {
     Left,
     Right,
+    /// A U-turn, which counts as two turns for energy and durations.
+    Around,
 }
 
 /// The types of instructions which the rover can receive
+#[derive(Copy, Clone, Debug, PartialEq)]
 enum Instruction {
     Forward,
+    /// Reverses one cell, still facing the same way.
+    Backward,
     Pivot(Spin),
+    /// Stays put for a tick, which only makes a difference when rovers run in lockstep.
+    Wait,
 }
 
 impl TryFrom<char> for Instruction {
     type Error = RoverError;
     fn try_from(value: char) -> Result<Self, Self::Error> {
+        Instruction::decode(value).ok_or(RoverError::InstructionError { received: value })
+    }
+}
+
+impl Instruction {
+    /// The instruction for a character, if there is one. Cheaper than `try_from` where there are
+    /// millions of them to get through, because there's no error to build.
+    fn decode(value: char) -> Option<Self> {
         match value {
-            'L' => Ok(Instruction::Pivot(Spin::Left)),
-            'R' => Ok(Instruction::Pivot(Spin::Right)),
-            'M' => Ok(Instruction::Forward),
-            _ => Err(RoverError::InstructionError { received: value }),
+            'L' => Some(Instruction::Pivot(Spin::Left)),
+            'R' => Some(Instruction::Pivot(Spin::Right)),
+            'U' => Some(Instruction::Pivot(Spin::Around)),
+            'M' => Some(Instruction::Forward),
+            'B' => Some(Instruction::Backward),
+            'W' => Some(Instruction::Wait),
+            _ => None,
         }
     }
+
+    /// Parses an instruction, only accepting the original `L`, `R` and `M` when `strict`.
+    fn parse(value: char, strict: bool) -> Result<Self, RoverError> {
+        if strict && !matches!(value, 'L' | 'R' | 'M') {
+            return Err(RoverError::InstructionError { received: value });
+        }
+        Instruction::try_from(value)
+    }
 }
 
 /// The position of the Rover at a point in time
-#[derive(Debug, PartialEq)]
-struct RoverPosition {
-    current_location: Coordinates,
-    facing: Direction,
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RoverPosition {
+    pub current_location: Coordinates,
+    pub facing: Direction,
+}
+
+/// The output format of the exercise, e.g. "1 3 N".
+impl std::fmt::Display for RoverPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let direction: String = self.facing.into();
+        write!(
+            f,
+            "{latitude} {longitude} {direction}",
+            latitude = self.current_location.latitude,
+            longitude = self.current_location.longitude,
+        )
+    }
 }
 
 impl TryFrom<String> for RoverPosition {
@@ -150,13 +252,14 @@ impl TryFrom<String> for RoverPosition {
 }
 
 // The position of the rover, and the (immutable, in implementation but not in rust compiler)
-// top right position of the Rover
-struct RoverInformation {
+// top right position of the Rover, along with the terrain it is driving over (if any).
+struct RoverInformation<'a> {
     position: RoverPosition,
     top_right_location: Coordinates,
+    terrain: Option<&'a Terrain>,
 }
 
-impl RoverInformation {
+impl<'a> RoverInformation<'a> {
     /// Parses coordinates and a position, so this is already quite far in the parsing of the
     /// instruction. Can fail if the start position of the rover is out of bounds. We don't have to
     /// worry about a negative location, because we are parsing the Coordinates as unsigned
@@ -164,7 +267,8 @@ impl RoverInformation {
     fn try_new(
         top_right: Coordinates,
         position: RoverPosition,
-    ) -> Result<RoverInformation, RoverError> {
+        terrain: Option<&'a Terrain>,
+    ) -> Result<RoverInformation<'a>, RoverError> {
         if position.current_location.longitude > top_right.longitude {
             Err(RoverError::OutOfBoundsStartPosition {
                 which: "North",
@@ -181,6 +285,7 @@ impl RoverInformation {
             Ok(RoverInformation {
                 position,
                 top_right_location: top_right,
+                terrain,
             })
         }
     }
@@ -197,95 +302,259 @@ impl RoverInformation {
             (&Direction::South, Spin::Left) => Direction::East,
             (&Direction::West, Spin::Right) => Direction::North,
             (&Direction::West, Spin::Left) => Direction::South,
+            (facing, Spin::Around) => facing.opposite(),
         };
         self.position.facing = new_facing;
     }
 
-    /// Tries to move the rover forward. If the rover will go out of bounds in any direction,
-    /// returns an err.
+    /// Finds where the rover would be if it moved forward, without moving it. If the rover would
+    /// go out of bounds, returns an err.
+    fn next_location(&self) -> Result<Coordinates, RoverError> {
+        self.location_towards(self.position.facing)
+    }
+
+    /// The direction the `instruction` would drive the rover in, if it drives at all.
+    fn heading_of(&self, instruction: &Instruction) -> Option<Direction> {
+        match instruction {
+            Instruction::Forward => Some(self.position.facing),
+            Instruction::Backward => Some(self.position.facing.opposite()),
+            Instruction::Pivot(_) | Instruction::Wait => None,
+        }
+    }
+
+    /// Finds where the rover would be if it moved one cell towards `heading`, without moving it,
+    /// whichever way it is facing. If the rover would go out of bounds in any direction, returns
+    /// an err.
     /// Pseudo-code I wrote for myself when implemnting:
     /// - [X] if move North and self.current_position.North == max_coord.north => Out of bounds err
     /// - [X] if move North, _ => self.current_position.long +=1
     /// - [X] if move South and self.current_position.South == 0 => Out of bounds err
     /// - [X] if move South, _ => self.current_position.long -=1
     /// - [X] same for East/West (sub East as North, West as South)
-    fn try_move_forward(&mut self) -> Result<(), RoverError> {
+    fn location_towards(&self, heading: Direction) -> Result<Coordinates, RoverError> {
         // There is more repetition than I would like here
         // It would be possible to reduce this with more complicated types
         // But this would not be likely to be less verbose
-        match self.position.facing {
+        let mut next = self.position.current_location;
+        let out_of_bounds = match heading {
             Direction::North => {
-                if self.position.current_location.longitude == self.top_right_location.longitude {
-                    Err(RoverError::OutOfBoundsError {
-                        direction: self.position.facing.into(),
-                    })
-                } else {
-                    self.position.current_location.longitude += 1;
-                    Ok(())
+                let at_edge = next.longitude == self.top_right_location.longitude;
+                if !at_edge {
+                    next.longitude += 1;
                 }
+                at_edge
             }
             Direction::South => {
-                if self.position.current_location.longitude == 0 {
-                    Err(RoverError::OutOfBoundsError {
-                        direction: self.position.facing.into(),
-                    })
-                } else {
-                    self.position.current_location.longitude -= 1;
-                    Ok(())
+                let at_edge = next.longitude == 0;
+                if !at_edge {
+                    next.longitude -= 1;
                 }
+                at_edge
             }
             Direction::East => {
-                if self.position.current_location.latitude == self.top_right_location.latitude {
-                    Err(RoverError::OutOfBoundsError {
-                        direction: self.position.facing.into(),
-                    })
-                } else {
-                    self.position.current_location.latitude += 1;
-                    Ok(())
+                let at_edge = next.latitude == self.top_right_location.latitude;
+                if !at_edge {
+                    next.latitude += 1;
                 }
+                at_edge
             }
             Direction::West => {
-                if self.position.current_location.latitude == 0 {
-                    Err(RoverError::OutOfBoundsError {
-                        direction: self.position.facing.into(),
-                    })
-                } else {
-                    self.position.current_location.latitude -= 1;
-                    Ok(())
+                let at_edge = next.latitude == 0;
+                if !at_edge {
+                    next.latitude -= 1;
                 }
+                at_edge
             }
+        };
+        if out_of_bounds {
+            Err(RoverError::OutOfBoundsError {
+                direction: heading.into(),
+            })
+        } else {
+            Ok(next)
+        }
+    }
+
+    /// How much higher the cell the `instruction` would drive the rover into is, or 0 if it is
+    /// lower, off the plateau, the instruction doesn't drive, or there is no terrain.
+    fn climb(&self, instruction: &Instruction) -> u64 {
+        let next = self
+            .heading_of(instruction)
+            .map(|h| self.location_towards(h));
+        match (self.terrain, next) {
+            (Some(terrain), Some(Ok(next))) => terrain.climb(self.position.current_location, next),
+            _ => 0,
+        }
+    }
+
+    /// Finds where the rover would be if it drove forward or backward (`heading` is the
+    /// direction it would go in), checking the slope as well as the bounds.
+    fn check_move(&self, heading: Direction) -> Result<Coordinates, RoverError> {
+        let next = self.location_towards(heading)?;
+        if let Some(terrain) = self.terrain {
+            terrain.check_slope(self.position.current_location, next)?;
         }
+        Ok(next)
     }
 
-    /// Mutates the rover by implementing the instruction to spin or move forward. Pivot/spins
-    /// cannot, fail, but move forwards can.
+    /// Tries to move the rover one cell towards `heading`. If the rover will go out of bounds in
+    /// any direction, or the slope to the next cell is too steep for it, returns an err.
+    fn try_move(&mut self, heading: Direction) -> Result<(), RoverError> {
+        let next = self.check_move(heading)?;
+        self.position.current_location = next;
+        Ok(())
+    }
+
+    /// Mutates the rover by implementing the instruction to spin or move. Pivot/spins
+    /// cannot, fail, but moves can.
     fn try_instruction(&mut self, instruction: Instruction) -> Result<(), RoverError> {
         match instruction {
             Instruction::Pivot(spin) => {
                 self.spin(spin);
                 Ok(())
             }
-            Instruction::Forward => self.try_move_forward(),
+            Instruction::Forward => self.try_move(self.position.facing),
+            Instruction::Backward => self.try_move(self.position.facing.opposite()),
+            Instruction::Wait => Ok(()),
         }
     }
 }
 
+/// Something to give each position a rover is in, and the timeline entry that got it there.
+type Visit<'v> = dyn FnMut(usize, &RoverPosition, Option<&TimelineEntry>) + 'v;
+
 /// Tries to process the rover from the received instructions.
 /// The amount of parsing here is relatively minimal - the top right co-ordinates have already been
 /// parsed, because they are always the same. However, the remaining start position/instructions
 /// have not been parsed so they can fail due to mal-formed inputs.
+///
+/// `rover` is the index of this rover within the mission, which is only used for the events and
+/// errors. With an energy model in the `options`, each instruction is paid for before it is
+/// executed, and with terrain, moves are checked against its slopes.
+///
+/// `visit`, if there is one, is given every position the rover is in: where it is deployed, and
+/// after each instruction, along with the instruction's entry in the timeline. Without one, the
+/// rover can skip along runs of the same instruction, rather than stepping through each of them.
 fn try_process_rover(
+    rover: usize,
     top_right: Coordinates,
     starting_position: String,
-    instructions: String,
+    program: &Program,
+    tracker: &mut Tracker,
+    options: &RunOptions,
+    mut visit: Option<&mut Visit>,
 ) -> Result<RoverPosition, RoverError> {
     let starting_position: RoverPosition = starting_position.try_into()?;
-    let mut rover_info = RoverInformation::try_new(top_right, starting_position)?;
-    let instruction_chars = instructions.chars();
-    for char in instruction_chars {
-        let instruction: Instruction = char.try_into()?;
-        rover_info.try_instruction(instruction)?;
+    let mut rover_info =
+        RoverInformation::try_new(top_right, starting_position, options.terrain.as_ref())?;
+    if let Some(visit) = visit.as_mut() {
+        visit(rover, &rover_info.position, None);
+    }
+    events::emit(RoverEvent::RoverDeployed {
+        rover,
+        position: rover_info.position.to_string(),
+    });
+    let mut battery = options.energy.as_ref().map(Battery::new);
+    let bytecode = Bytecode::compile(&program.steps, options.strict_alphabet);
+    // Runs of the same instruction can be done in one go when nothing needs to see each step.
+    let in_one_go = visit.is_none()
+        && battery.is_none()
+        && rover_info.terrain.is_none()
+        && !events::enabled(Level::Trace);
+    // Every step before an invalid one is a valid instruction, so one byte, so the step is also
+    // the offset in the steps.
+    let fail = |step: usize, instruction: char, e: RoverError| {
+        events::emit(RoverEvent::InstructionFailed {
+            rover,
+            step,
+            instruction,
+            offset: program.offset_of(step),
+            error: e.to_string(),
+        });
+        e
+    };
+    let mut step = 0;
+    let mut ops = bytecode.ops.as_slice();
+    // When the whole path fits on the plateau, nothing along the way can fail, so the rover can
+    // go straight to where it ends up.
+    if in_one_go && let Some(end) = NetTransform::of(ops).apply(rover_info.position, top_right) {
+        let steps = ops.iter().map(|op| op.count as usize).sum();
+        let ticks = ops
+            .iter()
+            .map(|op| options.durations.of(&op.instruction) * op.count as usize)
+            .sum();
+        rover_info.position = end;
+        tracker.advance(ticks);
+        tracker.instructions_executed(steps)?;
+        step = steps;
+        ops = &[];
+    }
+    for op in ops {
+        let instruction = op.instruction;
+        let char = char::from(program.steps.as_bytes()[step]);
+        let duration = options.durations.of(&instruction);
+        if in_one_go {
+            let result = rover_info.repeat(instruction, op.count as usize);
+            let done = result
+                .as_ref()
+                .map_or_else(|(done, _)| *done, |_| op.count as usize);
+            tracker.advance(duration * done);
+            tracker.instructions_executed(done)?;
+            step += done;
+            if let Err((_, e)) = result {
+                return Err(fail(step, char, e));
+            }
+            continue;
+        }
+        for _ in 0..op.count {
+            if let Some(battery) = battery.as_mut()
+                && !battery.try_spend(&instruction, rover_info.climb(&instruction))
+            {
+                let e = RoverError::OutOfEnergy {
+                    rover,
+                    position: rover_info.position.to_string(),
+                    remaining: program.steps[step..].to_string(),
+                };
+                return Err(fail(step, char, e));
+            }
+            if let Err(e) = rover_info.try_instruction(instruction) {
+                return Err(fail(step, char, e));
+            }
+            if let Some(battery) = battery.as_mut() {
+                battery.recharge_at(rover_info.position.current_location);
+            }
+            let start = tracker.advance(duration);
+            let entry = TimelineEntry {
+                rover,
+                step,
+                instruction: char,
+                start,
+                end: start + duration,
+                position: rover_info.position,
+            };
+            if let Some(visit) = visit.as_mut() {
+                visit(rover, &rover_info.position, Some(&entry));
+            }
+            if events::enabled(Level::Trace) {
+                events::emit(RoverEvent::InstructionExecuted {
+                    rover,
+                    step,
+                    tick: entry.end,
+                    instruction: char,
+                    position: rover_info.position.to_string(),
+                });
+            }
+            tracker.instruction_executed()?;
+            step += 1;
+        }
+    }
+    if let Some((step, char, e)) = bytecode.invalid {
+        return Err(fail(step, char, e));
     }
+    events::emit(RoverEvent::RoverFinished {
+        rover,
+        position: rover_info.position.to_string(),
+    });
     Ok(rover_info.position)
 }
 
@@ -305,26 +574,203 @@ pub fn return_coordinates(
     top_right: String,
     instructions: String,
 ) -> Result<Vec<String>, RoverError> {
-    let mut rovers = Vec::new();
-    let top_right_coordinates = top_right.try_into()?;
-    let parts: Vec<&str> = instructions.split("\n").collect();
+    return_coordinates_with_limits(top_right, instructions, &Limits::default())
+}
+
+/// The same as `return_coordinates`, but rejects the mission without simulating any of it if it
+/// breaks any of the `limits`.
+pub fn return_coordinates_with_limits(
+    top_right: String,
+    instructions: String,
+    limits: &Limits,
+) -> Result<Vec<String>, RoverError> {
+    let options = RunOptions {
+        limits: *limits,
+        ..Default::default()
+    };
+    return_coordinates_with_options(top_right, instructions, &options)
+}
+
+/// Everything which changes how a mission is run, other than the mission itself. The default is
+/// the same as `return_coordinates`.
+#[derive(Default)]
+pub struct RunOptions<'a> {
+    pub limits: Limits,
+    /// Stops the mission part way through when cancelled, e.g. from another thread.
+    pub cancellation: Option<CancellationToken>,
+    /// Called with the progress through the mission, every `PROGRESS_INTERVAL` instructions and
+    /// after each rover finishes. `Sync`, so that the same options can be shared by missions
+    /// running on a thread pool.
+    pub on_progress: Option<&'a (dyn Fn(Progress) + Sync)>,
+    /// Gives each rover a battery, which the instructions drain. `None` means unlimited energy.
+    pub energy: Option<EnergyModel>,
+    /// The elevation of the plateau, and how steep a slope the rovers can drive up or down.
+    /// `None` means the plateau is flat.
+    pub terrain: Option<Terrain>,
+    /// How many ticks each kind of instruction takes.
+    pub durations: Durations,
+    /// Only accepts the `L`, `R` and `M` of the original problem, so that `W`, `B` and `U` fail
+    /// with `RoverError::InstructionError`, the same as any other letter.
+    pub strict_alphabet: bool,
+}
+
+/// The same as `return_coordinates`, with the `options` for limits, cancellation, progress,
+/// energy and terrain.
+pub fn return_coordinates_with_options(
+    top_right: String,
+    instructions: String,
+    options: &RunOptions,
+) -> Result<Vec<String>, RoverError> {
+    let positions = return_positions_with_options(top_right, instructions, options)?;
+    Ok(positions.iter().map(ToString::to_string).collect())
+}
+
+/// The same as `return_coordinates_with_options`, but returns the final positions themselves,
+/// rather than formatting them as strings. This is for callers which want to do something with
+/// the positions other than print them, e.g. the python bindings.
+pub fn return_positions_with_options(
+    top_right: String,
+    instructions: String,
+    options: &RunOptions,
+) -> Result<Vec<RoverPosition>, RoverError> {
+    run_mission(top_right, instructions, options, None)
+}
+
+/// A rover's start position as it appears in the mission, and its instructions.
+type RoverLines<'m> = (&'m str, Program);
+
+/// A rover's start position and instructions, both as they appear in the mission.
+type WrittenLines<'m> = (&'m str, &'m str);
+
+/// Checks the mission against the `options`, and splits it into each rover's start position and
+/// instructions, without running any of it. Returns the top right of the plateau too.
+fn parse_mission<'m>(
+    top_right: String,
+    instructions: &'m str,
+    options: &RunOptions,
+) -> Result<(Coordinates, Vec<RoverLines<'m>>), RoverError> {
+    check_mission(top_right, options, || split_mission(instructions, options))
+}
+
+/// Checks the plateau against the `options`, then splits the mission into rovers with `split`.
+/// Every kind of mission goes through here, so that they're checked in the same order, and all
+/// emit `RoverEvent::MissionParsed`.
+pub(crate) fn check_mission<R>(
+    top_right: String,
+    options: &RunOptions,
+    split: impl FnOnce() -> Result<Vec<R>, RoverError>,
+) -> Result<(Coordinates, Vec<R>), RoverError> {
+    let limits = &options.limits;
+    let top_right_coordinates = top_right.clone().try_into()?;
+    limits.check_plateau(top_right_coordinates)?;
+    if let Some(terrain) = &options.terrain {
+        terrain.heightmap.check_covers(top_right_coordinates)?;
+    }
+    let rovers = split()?;
+    events::emit(RoverEvent::MissionParsed {
+        top_right,
+        rovers: rovers.len(),
+    });
+    Ok((top_right_coordinates, rovers))
+}
+
+/// Takes the macro definitions out of the mission, and pairs up the rest as each rover's start
+/// position and instructions, as written.
+///
+/// The limits on the number of rovers and the length of their instructions are checked here,
+/// before anything is expanded. A strict alphabet has no counts, groups or macros, so a line
+/// starting with `def` is just another line.
+pub(crate) fn mission_lines<'m>(
+    instructions: &'m str,
+    options: &RunOptions,
+) -> Result<(Macros, Vec<WrittenLines<'m>>), RoverError> {
+    let limits = &options.limits;
+    limits.check_rovers(instructions, options.strict_alphabet)?;
+    let max_total = limits.max_total_steps.unwrap_or(MAX_EXPANDED_TOTAL);
+    let mut macros = Macros::with_max_total(max_total);
+    let mut parts: Vec<&str> = Vec::new();
+    for (index, line) in instructions.split("\n").enumerate() {
+        match line.strip_prefix(DEFINITION) {
+            Some(definition) if !options.strict_alphabet => macros.define(definition, index + 1)?,
+            _ => parts.push(line),
+        }
+    }
     let pairs = parts.chunks_exact(2);
     if !pairs.remainder().is_empty() {
         return Err(RoverError::InvalidInput);
     }
-    for pair in pairs {
-        let starting_position = pair[0].to_string();
-        let instruction_str = pair[1].to_string();
+    limits.check_instructions(pairs.clone().map(|pair| pair[1]))?;
+    Ok((macros, pairs.map(|pair| (pair[0], pair[1])).collect()))
+}
 
-        let rover_final_position =
-            try_process_rover(top_right_coordinates, starting_position, instruction_str)?;
-        let direction: String = rover_final_position.facing.into();
-        let position_as_string = format!(
-            "{latitude} {longitude} {direction}",
-            latitude = rover_final_position.current_location.latitude,
-            longitude = rover_final_position.current_location.longitude,
-        );
-        rovers.push(position_as_string);
+/// Splits the mission into each rover's start position and expanded instructions.
+///
+/// The limits on the length of the instructions are checked against the instructions as written,
+/// and again once any counts, groups and macros are expanded, so that a few characters can't stand
+/// for more steps than the limits allow.
+fn split_mission<'m>(
+    instructions: &'m str,
+    options: &RunOptions,
+) -> Result<Vec<RoverLines<'m>>, RoverError> {
+    let limits = &options.limits;
+    let (macros, lines) = mission_lines(instructions, options)?;
+    let max_steps = limits.max_instruction_len.unwrap_or(MAX_EXPANDED_STEPS);
+    let rovers = lines
+        .into_iter()
+        .enumerate()
+        .map(|(rover, (position, source))| {
+            let program = if options.strict_alphabet {
+                Program::plain(source)
+            } else {
+                Program::parse(source, rover, max_steps, &macros)?
+            };
+            Ok((position, program))
+        })
+        .collect::<Result<Vec<_>, RoverError>>()?;
+    limits.check_instructions(rovers.iter().map(|(_, program)| program.steps.as_str()))?;
+    Ok(rovers)
+}
+
+/// Returns the mission as it will be run: each rover's start position, and its instructions with
+/// every count, group and macro expanded, without the macro definitions. This is for checking what
+/// a mission written with macros actually does, without running it.
+///
+/// This fails the same as `return_coordinates_with_options` would on anything in the mission
+/// other than the plateau, before running any of it.
+pub fn expand_mission(instructions: String, options: &RunOptions) -> Result<String, RoverError> {
+    let rovers = split_mission(&instructions, options)?;
+    let lines: Vec<&str> = rovers
+        .iter()
+        .flat_map(|(position, program)| [*position, program.steps.as_str()])
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// Runs the mission, giving any `visit` the index of the rover and its position, for every position
+/// that each rover is in along the way, and the timeline entry of the instruction which got it
+/// there. Returns the final positions.
+fn run_mission(
+    top_right: String,
+    instructions: String,
+    options: &RunOptions,
+    mut visit: Option<&mut Visit>,
+) -> Result<Vec<RoverPosition>, RoverError> {
+    let mut tracker = Tracker::new(options.cancellation.as_ref(), options.on_progress);
+    let mut rovers = Vec::new();
+    let (top_right_coordinates, pairs) = parse_mission(top_right, &instructions, options)?;
+    tracker.start()?;
+    for (rover, (starting_position, program)) in pairs.into_iter().enumerate() {
+        let rover_final_position = try_process_rover(
+            rover,
+            top_right_coordinates,
+            starting_position.to_string(),
+            &program,
+            &mut tracker,
+            options,
+            visit.as_deref_mut(),
+        )?;
+        tracker.rover_completed()?;
+        rovers.push(rover_final_position);
     }
     Ok(rovers)
 }
@@ -404,4 +850,28 @@ MMXLMM"
         dbg!(&res);
         assert!(matches!(res, Err(RoverError::InstructionError { .. })));
     }
+
+    #[test]
+    fn test_backward_and_u_turn() {
+        let top_right = "5 5".to_string();
+        // Reversing out of a dead end, rather than RRMRR.
+        let instructions = "1 2 N\nMBBU\n0 0 E\nB".to_string();
+        let res = return_coordinates(top_right.clone(), instructions);
+        assert!(matches!(
+            res,
+            Err(RoverError::OutOfBoundsError { ref direction }) if direction == "W"
+        ));
+
+        let instructions = "1 2 N\nMBBU".to_string();
+        assert_eq!(
+            return_coordinates(top_right.clone(), instructions.clone()),
+            Ok(vec!["1 1 S".to_string()])
+        );
+        let strict = RunOptions {
+            strict_alphabet: true,
+            ..Default::default()
+        };
+        let res = return_coordinates_with_options(top_right, instructions, &strict);
+        assert_eq!(res, Err(RoverError::InstructionError { received: 'B' }));
+    }
 }
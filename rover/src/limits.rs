@@ -0,0 +1,175 @@
+//! Limits on the size of a mission, so that a hostile (or just broken) input can be rejected
+//! cheaply, before any of it is simulated.
+
+use crate::Coordinates;
+use crate::error::RoverError;
+use crate::program::DEFINITION;
+
+/// Resource limits for a mission. `None` means unlimited, which is the default.
+///
+/// All of these are checked up front, before any rover is moved, so a mission which breaks a limit
+/// costs roughly one pass over the input string.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// The maximum number of rovers (start position/instruction pairs) in one mission.
+    pub max_rovers: Option<usize>,
+    /// The maximum length of a single rover's instructions, in bytes. Every valid instruction is
+    /// a single byte, so this is also the maximum number of instructions.
+    pub max_instruction_len: Option<usize>,
+    /// The maximum number of cells on the plateau. The plateau includes 0, so a top right of
+    /// "5 5" is 36 cells.
+    pub max_plateau_cells: Option<u64>,
+    /// The maximum number of instructions across every rover in the mission. Each macro which is
+    /// called also counts once, for its expanded instructions, since they're written out too.
+    pub max_total_steps: Option<usize>,
+}
+
+impl Limits {
+    pub(crate) fn check_plateau(&self, top_right: Coordinates) -> Result<(), RoverError> {
+        let cells = (top_right.latitude as u64 + 1) * (top_right.longitude as u64 + 1);
+        match self.max_plateau_cells {
+            Some(max) if cells > max => Err(RoverError::PlateauTooLarge { max, got: cells }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the number of rovers from the raw input, so that it can be done before the input is
+    /// split up. Two lines per rover, with any odd line out rounded up, so that this error is
+    /// given in preference to `RoverError::InvalidInput`. Macro definitions aren't rovers' lines,
+    /// except with a `strict` alphabet, which has no macros, so they're counted like any other.
+    pub(crate) fn check_rovers(&self, instructions: &str, strict: bool) -> Result<(), RoverError> {
+        let Some(max) = self.max_rovers else {
+            return Ok(());
+        };
+        let lines = instructions
+            .split('\n')
+            .filter(|line| strict || !line.starts_with(DEFINITION))
+            .count();
+        let rovers = lines.div_ceil(2);
+        if rovers > max {
+            Err(RoverError::TooManyRovers { max, got: rovers })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks the length of each rover's instructions, and the total across all of them.
+    pub(crate) fn check_instructions<'a>(
+        &self,
+        rover_instructions: impl Iterator<Item = &'a str>,
+    ) -> Result<(), RoverError> {
+        let mut total: usize = 0;
+        for (rover, instructions) in rover_instructions.enumerate() {
+            let len = instructions.len();
+            if let Some(max) = self.max_instruction_len
+                && len > max
+            {
+                return Err(RoverError::InstructionsTooLong {
+                    rover,
+                    max,
+                    got: len,
+                });
+            }
+            total += len;
+            if let Some(max) = self.max_total_steps
+                && total > max
+            {
+                return Err(RoverError::TooManySteps { max });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::return_coordinates_with_limits;
+
+    const TOP_RIGHT: &str = "5 5";
+    const INSTRUCTIONS: &str = "1 2 N
+LMLMLMLMM
+3 3 E
+MMRMMRMRRM";
+
+    fn run(limits: Limits) -> Result<Vec<String>, RoverError> {
+        return_coordinates_with_limits(TOP_RIGHT.to_string(), INSTRUCTIONS.to_string(), &limits)
+    }
+
+    #[test]
+    fn limits_at_exact_size_pass() {
+        let limits = Limits {
+            max_rovers: Some(2),
+            max_instruction_len: Some(10),
+            max_plateau_cells: Some(36),
+            max_total_steps: Some(19),
+        };
+        assert_eq!(
+            run(limits),
+            Ok(vec!["1 3 N".to_string(), "5 1 E".to_string()])
+        );
+    }
+
+    #[test]
+    fn too_many_rovers() {
+        let limits = Limits {
+            max_rovers: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            run(limits),
+            Err(RoverError::TooManyRovers { max: 1, got: 2 })
+        );
+    }
+
+    #[test]
+    fn macro_definitions_are_only_skipped_with_macros() {
+        let limits = Limits {
+            max_rovers: Some(1),
+            ..Default::default()
+        };
+        let instructions = "def SQUARE = 4(MR)\n1 2 N\nM";
+        assert_eq!(limits.check_rovers(instructions, false), Ok(()));
+        assert_eq!(
+            limits.check_rovers(instructions, true),
+            Err(RoverError::TooManyRovers { max: 1, got: 2 })
+        );
+    }
+
+    #[test]
+    fn instructions_too_long() {
+        let limits = Limits {
+            max_instruction_len: Some(9),
+            ..Default::default()
+        };
+        assert_eq!(
+            run(limits),
+            Err(RoverError::InstructionsTooLong {
+                rover: 1,
+                max: 9,
+                got: 10
+            })
+        );
+    }
+
+    #[test]
+    fn plateau_too_large() {
+        let limits = Limits {
+            max_plateau_cells: Some(35),
+            ..Default::default()
+        };
+        assert_eq!(
+            run(limits),
+            Err(RoverError::PlateauTooLarge { max: 35, got: 36 })
+        );
+    }
+
+    #[test]
+    fn too_many_steps() {
+        let limits = Limits {
+            max_total_steps: Some(18),
+            ..Default::default()
+        };
+        assert_eq!(run(limits), Err(RoverError::TooManySteps { max: 18 }));
+    }
+}
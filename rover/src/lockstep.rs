@@ -0,0 +1,684 @@
+//! Runs every rover at the same time, one instruction per tick, rather than one rover after
+//! another.
+//!
+//! On every tick, every rover which still has instructions carries out its next. Turning never
+//! conflicts, but two kinds of move do:
+//! - Two or more rovers ending the tick in the same cell, including a rover driving into a cell
+//!   where another rover is staying put.
+//! - Two rovers swapping cells, which would mean driving through each other.
+//!
+//! Every rover in a conflict which was trying to move is blocked instead: it stays where it is,
+//! and its instruction costs no energy. Blocking one rover can leave it in the way of another, so
+//! this repeats until there are no conflicts left. A rover following right behind another isn't a
+//! conflict, as long as the one in front moves. Rovers may be deployed on the same cell; only moves
+//! conflict.
+//!
+//! What happens to a blocked instruction is up to the caller (see `Blocked`). By default it is
+//! just used up. Rovers can instead wait and try it again on the next tick, which is closer to
+//! what real rovers would do, but then they can get stuck waiting on each other forever:
+//! - A cyclic wait (a deadlock), where each rover in a cycle is waiting to drive into the cell of
+//!   the next, e.g. two rovers trying to swap. This is found from the rovers' wait-for graph.
+//! - A repeated global state (a livelock), where the rovers keep conflicting without any of them
+//!   getting anywhere, e.g. two rovers both trying to get into the same empty cell.
+//!
+//! Either way, the rovers involved and the tick it started are reported, and it is resolved with
+//! the `DeadlockPolicy`.
+//!
+//! An instruction which takes more than one tick (see `Durations`) keeps the rover in its cell
+//! until the last of them, which is when the instruction happens, and when it can conflict. So a
+//! slow move is a wait followed by a quick move, and a blocked rover which waits only tries the
+//! last tick again.
+
+use crate::energy::Battery;
+use crate::error::RoverError;
+use crate::events::{self, RoverEvent};
+use crate::program::Program;
+use crate::progress::Tracker;
+use crate::timeline::TimelineEntry;
+use crate::{Coordinates, Instruction, RoverInformation, RoverPosition, RunOptions, Spin};
+use log::Level;
+use std::collections::{BTreeMap, VecDeque};
+
+/// How rovers got in each other's way.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConflictKind {
+    /// More than one rover would have ended the tick in the same cell.
+    SameCell,
+    /// Two rovers would have driven through each other.
+    Swap,
+}
+
+impl ConflictKind {
+    fn name(self) -> &'static str {
+        match self {
+            ConflictKind::SameCell => "same_cell",
+            ConflictKind::Swap => "swap",
+        }
+    }
+}
+
+/// Rovers which got in each other's way on one tick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub tick: usize,
+    pub kind: ConflictKind,
+    /// Every rover involved, in ascending order, including any which weren't trying to move.
+    pub rovers: Vec<usize>,
+    /// The cell the rovers were trying to get into. For a swap, this is the cell the first of
+    /// the two rovers was trying to get into.
+    pub cell: Coordinates,
+}
+
+/// What happens to an instruction which is blocked by a conflict.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Blocked {
+    /// The instruction is used up without being carried out, so a rover is never held up.
+    #[default]
+    Skip,
+    /// The rover waits, and tries the same instruction again on the next tick. Rovers which get
+    /// stuck waiting on each other are resolved with the policy.
+    Wait(DeadlockPolicy),
+}
+
+/// How to get rovers which are stuck waiting on each other moving again.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DeadlockPolicy {
+    /// Fail the mission with `RoverError::Deadlock`.
+    Abort,
+    /// The rover with the lowest priority gives up its blocked instruction, and the others keep
+    /// waiting. Rovers earlier in the mission have priority over later ones.
+    YieldByPriority,
+    /// Every rover involved gives up its blocked instruction.
+    DropBlocked,
+}
+
+/// How rovers were found to be stuck.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DeadlockKind {
+    /// Each rover was waiting to drive into the cell of the next, in a cycle.
+    CyclicWait,
+    /// A whole tick went by without any rover getting anywhere, so the next would be the same.
+    RepeatedState,
+}
+
+impl DeadlockKind {
+    fn name(self) -> &'static str {
+        match self {
+            DeadlockKind::CyclicWait => "cyclic_wait",
+            DeadlockKind::RepeatedState => "repeated_state",
+        }
+    }
+}
+
+/// Rovers which were stuck waiting on each other, before the `DeadlockPolicy` was applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deadlock {
+    /// The tick that the rovers started waiting on each other.
+    pub tick: usize,
+    pub kind: DeadlockKind,
+    /// The rovers involved, in ascending order.
+    pub rovers: Vec<usize>,
+}
+
+/// The result of `run_lockstep`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockstepResult {
+    /// The final position of each rover, in input order.
+    pub positions: Vec<RoverPosition>,
+    /// Every conflict, in the order they happened. A rover which waits gets into a conflict on
+    /// every tick that it is blocked.
+    pub conflicts: Vec<Conflict>,
+    /// Every time rovers got stuck waiting on each other, in the order they were found. Always
+    /// empty with `Blocked::Skip`.
+    pub deadlocks: Vec<Deadlock>,
+    /// Every instruction which was carried out, in the order they finished. Blocked instructions
+    /// which were given up on aren't in it.
+    pub timeline: Vec<TimelineEntry>,
+}
+
+/// What a rover is trying to do on this tick.
+enum Intent {
+    Idle,
+    /// Part way through an instruction which takes more than one tick.
+    Busy,
+    Wait,
+    Spin(Spin),
+    Move(Coordinates),
+}
+
+struct LockstepRover<'a> {
+    info: RoverInformation<'a>,
+    battery: Option<Battery<'a>>,
+    instructions: Vec<char>,
+    /// Where the `instructions` came from, for reporting failures.
+    program: Program,
+    /// The index of the next instruction to carry out.
+    next: usize,
+    /// The tick this rover was first blocked on its next instruction, if it is waiting.
+    waiting_since: Option<usize>,
+    /// The tick this rover started on its next instruction, if it has.
+    started: Option<usize>,
+    /// How many ticks of its next instruction this rover has done.
+    elapsed: usize,
+}
+
+impl LockstepRover<'_> {
+    fn finished(&self) -> bool {
+        self.next == self.instructions.len()
+    }
+
+    /// Moves on to the instruction after the next, whether the next was carried out or given up
+    /// on.
+    fn next_instruction(&mut self) {
+        self.next += 1;
+        self.waiting_since = None;
+        self.started = None;
+        self.elapsed = 0;
+    }
+}
+
+/// Runs the mission with every rover moving at the same time, rather than one after another.
+///
+/// This takes the same input and options as `return_coordinates_with_options`, and fails in the
+/// same ways, but conflicts between rovers don't fail the mission: the conflicting moves are
+/// blocked, and every conflict is returned along with the final positions. `on_blocked` is what
+/// happens to the blocked instructions.
+pub fn run_lockstep(
+    top_right: String,
+    instructions: String,
+    options: &RunOptions,
+    on_blocked: Blocked,
+) -> Result<LockstepResult, RoverError> {
+    let mut tracker = Tracker::new(options.cancellation.as_ref(), options.on_progress);
+    let (top_right, pairs) = crate::parse_mission(top_right, &instructions, options)?;
+    let mut rovers = Vec::with_capacity(pairs.len());
+    for (rover, (starting_position, program)) in pairs.into_iter().enumerate() {
+        let position: RoverPosition = starting_position.to_string().try_into()?;
+        let info = RoverInformation::try_new(top_right, position, options.terrain.as_ref())?;
+        events::emit(RoverEvent::RoverDeployed {
+            rover,
+            position: info.position.to_string(),
+        });
+        rovers.push(LockstepRover {
+            info,
+            battery: options.energy.as_ref().map(Battery::new),
+            instructions: program.steps.chars().collect(),
+            program,
+            next: 0,
+            waiting_since: None,
+            started: None,
+            elapsed: 0,
+        });
+    }
+    tracker.start()?;
+    let mut finished = vec![false; rovers.len()];
+    finish_rovers(&rovers, &mut finished, &mut tracker)?;
+
+    let mut conflicts = Vec::new();
+    let mut deadlocks = Vec::new();
+    let mut timeline = Vec::new();
+    let mut tick = 0;
+    while finished.contains(&false) {
+        let intents = rovers
+            .iter()
+            .enumerate()
+            .map(|(rover, state)| intent(rover, state, options))
+            .collect::<Result<Vec<_>, _>>()?;
+        let blocked = resolve_conflicts(&rovers, &intents, tick, &mut conflicts);
+        // Every rover in a cycle is blocked, so stays put, so this has to be found before any of
+        // the other rovers move.
+        let cycles = cyclic_waits(&rovers, &intents, &blocked);
+
+        let mut progressed = false;
+        for (rover, (state, intent)) in rovers.iter_mut().zip(intents).enumerate() {
+            if matches!(intent, Intent::Idle) {
+                continue;
+            }
+            state.started.get_or_insert(tick);
+            if matches!(intent, Intent::Busy) {
+                state.elapsed += 1;
+                progressed = true;
+                continue;
+            }
+            if blocked[rover] {
+                match on_blocked {
+                    Blocked::Skip => {
+                        state.next_instruction();
+                        progressed = true;
+                    }
+                    Blocked::Wait(_) => {
+                        state.waiting_since.get_or_insert(tick);
+                    }
+                }
+                continue;
+            }
+            timeline.push(execute(rover, state, intent, tick)?);
+            progressed = true;
+            tracker.instruction_executed()?;
+        }
+
+        if let Blocked::Wait(policy) = on_blocked {
+            let mut stuck: Vec<Deadlock> = cycles
+                .into_iter()
+                .map(|rovers_in_cycle| Deadlock {
+                    // The cycle closed when the last of its rovers started waiting.
+                    tick: rovers_in_cycle
+                        .iter()
+                        .filter_map(|r| rovers[*r].waiting_since)
+                        .max()
+                        .unwrap_or(tick),
+                    kind: DeadlockKind::CyclicWait,
+                    rovers: rovers_in_cycle,
+                })
+                .collect();
+            // A rover's place in its instructions only ever moves forward, so the state of every
+            // rover can only come round again if nobody got anywhere. Then it is the same as at
+            // the start of this tick, and would be at the start of every tick after it.
+            if stuck.is_empty() && !progressed {
+                stuck.push(Deadlock {
+                    tick,
+                    kind: DeadlockKind::RepeatedState,
+                    rovers: (0..rovers.len())
+                        .filter(|r| rovers[*r].waiting_since.is_some())
+                        .collect(),
+                });
+            }
+            for deadlock in stuck {
+                events::emit(RoverEvent::Deadlock {
+                    tick: deadlock.tick,
+                    kind: deadlock.kind.name(),
+                    rovers: deadlock.rovers.clone(),
+                });
+                resolve_deadlock(&mut rovers, &deadlock, policy)?;
+                deadlocks.push(deadlock);
+            }
+        }
+        finish_rovers(&rovers, &mut finished, &mut tracker)?;
+        tick += 1;
+    }
+    Ok(LockstepResult {
+        positions: rovers.iter().map(|r| r.info.position).collect(),
+        conflicts,
+        deadlocks,
+        timeline,
+    })
+}
+
+/// Reports every rover which has just run out of instructions.
+fn finish_rovers(
+    rovers: &[LockstepRover],
+    finished: &mut [bool],
+    tracker: &mut Tracker,
+) -> Result<(), RoverError> {
+    for (rover, state) in rovers.iter().enumerate() {
+        if state.finished() && !finished[rover] {
+            finished[rover] = true;
+            events::emit(RoverEvent::RoverFinished {
+                rover,
+                position: state.info.position.to_string(),
+            });
+            tracker.rover_completed()?;
+        }
+    }
+    Ok(())
+}
+
+/// Emits the event for the rover's next instruction failing, and passes the error on.
+fn failed(rover: usize, state: &LockstepRover, error: RoverError) -> RoverError {
+    events::emit(RoverEvent::InstructionFailed {
+        rover,
+        step: state.next,
+        instruction: state.instructions[state.next],
+        offset: state.program.offset_of(state.next),
+        error: error.to_string(),
+    });
+    error
+}
+
+/// Works out what the rover is trying to do on this tick, failing if it's an invalid instruction
+/// or a move off the plateau or up a slope that's too steep.
+fn intent(rover: usize, state: &LockstepRover, options: &RunOptions) -> Result<Intent, RoverError> {
+    let Some(&char) = state.instructions.get(state.next) else {
+        return Ok(Intent::Idle);
+    };
+    let check = || match Instruction::parse(char, options.strict_alphabet)? {
+        instruction if state.elapsed + 1 < options.durations.of(&instruction) => Ok(Intent::Busy),
+        Instruction::Pivot(spin) => Ok(Intent::Spin(spin)),
+        Instruction::Wait => Ok(Intent::Wait),
+        Instruction::Forward => {
+            let facing = state.info.position.facing;
+            Ok(Intent::Move(state.info.check_move(facing)?))
+        }
+        Instruction::Backward => {
+            let behind = state.info.position.facing.opposite();
+            Ok(Intent::Move(state.info.check_move(behind)?))
+        }
+    };
+    check().map_err(|e| failed(rover, state, e))
+}
+
+/// Finds every conflict between the rovers' intents on this tick, adds them to `conflicts`, and
+/// returns which rovers are blocked.
+fn resolve_conflicts(
+    rovers: &[LockstepRover],
+    intents: &[Intent],
+    tick: usize,
+    conflicts: &mut Vec<Conflict>,
+) -> Vec<bool> {
+    let current: Vec<Coordinates> = rovers
+        .iter()
+        .map(|r| r.info.position.current_location)
+        .collect();
+    let mut targets = current.clone();
+    let mut moving = vec![false; rovers.len()];
+    for (rover, intent) in intents.iter().enumerate() {
+        if let Intent::Move(next) = intent {
+            targets[rover] = *next;
+            moving[rover] = true;
+        }
+    }
+    let mut blocked = vec![false; rovers.len()];
+    let mut conflict = |kind, rovers: Vec<usize>, cell| {
+        events::emit(RoverEvent::Conflict {
+            tick,
+            kind: ConflictKind::name(kind),
+            rovers: rovers.clone(),
+            cell: Coordinates::to_string(&cell),
+        });
+        conflicts.push(Conflict {
+            tick,
+            kind,
+            rovers,
+            cell,
+        });
+    };
+
+    loop {
+        let mut newly_blocked = Vec::new();
+        for a in 0..rovers.len() {
+            for b in a + 1..rovers.len() {
+                let swap = targets[a] == current[b] && targets[b] == current[a];
+                if moving[a] && moving[b] && swap {
+                    conflict(ConflictKind::Swap, vec![a, b], targets[a]);
+                    newly_blocked.extend([a, b]);
+                }
+            }
+        }
+        let mut by_cell: BTreeMap<Coordinates, Vec<usize>> = BTreeMap::new();
+        for (rover, target) in targets.iter().enumerate() {
+            if !newly_blocked.contains(&rover) {
+                by_cell.entry(*target).or_default().push(rover);
+            }
+        }
+        for (cell, group) in by_cell {
+            let movers: Vec<usize> = group.iter().copied().filter(|r| moving[*r]).collect();
+            if group.len() > 1 && !movers.is_empty() {
+                conflict(ConflictKind::SameCell, group, cell);
+                newly_blocked.extend(movers);
+            }
+        }
+
+        if newly_blocked.is_empty() {
+            return blocked;
+        }
+        for rover in newly_blocked {
+            blocked[rover] = true;
+            moving[rover] = false;
+            targets[rover] = current[rover];
+        }
+    }
+}
+
+/// Finds every group of rovers which are waiting on each other in a cycle. A blocked rover waits
+/// on any rover in the cell that it's trying to drive into.
+fn cyclic_waits(rovers: &[LockstepRover], intents: &[Intent], blocked: &[bool]) -> Vec<Vec<usize>> {
+    let waits_on: Vec<Vec<usize>> = intents
+        .iter()
+        .enumerate()
+        .map(|(a, intent)| match intent {
+            Intent::Move(target) if blocked[a] => (0..rovers.len())
+                .filter(|b| *b != a && rovers[*b].info.position.current_location == *target)
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect();
+    // Which rovers each waiting rover is waiting on, directly or through others.
+    let reaches = |from: usize| {
+        let mut seen = vec![false; rovers.len()];
+        let mut queue = VecDeque::from(waits_on[from].clone());
+        while let Some(rover) = queue.pop_front() {
+            if !std::mem::replace(&mut seen[rover], true) {
+                queue.extend(&waits_on[rover]);
+            }
+        }
+        seen
+    };
+    let reach: Vec<Option<Vec<bool>>> = (0..rovers.len())
+        .map(|rover| (!waits_on[rover].is_empty()).then(|| reaches(rover)))
+        .collect();
+    let in_cycle = |a: usize, b: usize| {
+        let reached = |from: usize, to: usize| reach[from].as_ref().is_some_and(|r| r[to]);
+        reached(a, b) && reached(b, a)
+    };
+
+    let mut cycles = Vec::new();
+    let mut grouped = vec![false; rovers.len()];
+    for rover in 0..rovers.len() {
+        if grouped[rover] || !in_cycle(rover, rover) {
+            continue;
+        }
+        let cycle: Vec<usize> = (0..rovers.len())
+            .filter(|other| in_cycle(rover, *other))
+            .collect();
+        for other in &cycle {
+            grouped[*other] = true;
+        }
+        cycles.push(cycle);
+    }
+    cycles
+}
+
+/// Gets stuck rovers moving again with the `policy`, or fails the mission.
+fn resolve_deadlock(
+    rovers: &mut [LockstepRover],
+    deadlock: &Deadlock,
+    policy: DeadlockPolicy,
+) -> Result<(), RoverError> {
+    match policy {
+        DeadlockPolicy::Abort => {
+            return Err(RoverError::Deadlock {
+                rovers: format!("{:?}", deadlock.rovers),
+                tick: deadlock.tick,
+            });
+        }
+        DeadlockPolicy::YieldByPriority => {
+            if let Some(lowest) = deadlock.rovers.iter().max() {
+                rovers[*lowest].next_instruction();
+            }
+        }
+        DeadlockPolicy::DropBlocked => {
+            for rover in &deadlock.rovers {
+                rovers[*rover].next_instruction();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pays for and carries out the rover's next instruction on `tick`, which has already been
+/// checked, and moves on to the instruction after.
+fn execute(
+    rover: usize,
+    state: &mut LockstepRover,
+    intent: Intent,
+    tick: usize,
+) -> Result<TimelineEntry, RoverError> {
+    let char = state.instructions[state.next];
+    if let Some(battery) = state.battery.as_mut() {
+        // This was already parsed by `intent`, so can't fail.
+        let instruction = Instruction::try_from(char)?;
+        if !battery.try_spend(&instruction, state.info.climb(&instruction)) {
+            let error = RoverError::OutOfEnergy {
+                rover,
+                position: state.info.position.to_string(),
+                remaining: state.instructions[state.next..].iter().collect(),
+            };
+            return Err(failed(rover, state, error));
+        }
+    }
+    match intent {
+        Intent::Spin(spin) => state.info.spin(spin),
+        Intent::Move(next) => state.info.position.current_location = next,
+        Intent::Idle | Intent::Busy | Intent::Wait => {}
+    }
+    if let Some(battery) = state.battery.as_mut() {
+        battery.recharge_at(state.info.position.current_location);
+    }
+    let entry = TimelineEntry {
+        rover,
+        step: state.next,
+        instruction: char,
+        start: state.started.unwrap_or(tick),
+        end: tick + 1,
+        position: state.info.position,
+    };
+    if events::enabled(Level::Trace) {
+        events::emit(RoverEvent::InstructionExecuted {
+            rover,
+            step: entry.step,
+            tick: entry.end,
+            instruction: char,
+            position: state.info.position.to_string(),
+        });
+    }
+    state.next_instruction();
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::at;
+
+    fn run(instructions: &str) -> LockstepResult {
+        run_with(instructions, Blocked::Skip).unwrap()
+    }
+
+    fn run_with(instructions: &str, on_blocked: Blocked) -> Result<LockstepResult, RoverError> {
+        run_lockstep(
+            "5 5".to_string(),
+            instructions.to_string(),
+            &RunOptions::default(),
+            on_blocked,
+        )
+    }
+
+    fn positions(result: &LockstepResult) -> Vec<String> {
+        result.positions.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn independent_rovers_match_sequential() {
+        let result = run("1 2 N\nLMLMLMLMM\n3 3 E\nMMRMMRMRRM");
+        assert_eq!(positions(&result), vec!["1 3 N", "5 1 E"]);
+        assert!(result.conflicts.is_empty());
+
+        // Following right behind is fine, as the rover in front moves out of the way.
+        let result = run("0 0 E\nMM\n1 0 E\nMM");
+        assert_eq!(positions(&result), vec!["2 0 E", "3 0 E"]);
+        assert!(result.conflicts.is_empty());
+    }
+
+    #[test]
+    fn same_cell_and_swap_are_blocked() {
+        let result = run("0 0 E\nM\n2 0 W\nM");
+        assert_eq!(positions(&result), vec!["0 0 E", "2 0 W"]);
+        assert_eq!(
+            result.conflicts,
+            vec![Conflict {
+                tick: 0,
+                kind: ConflictKind::SameCell,
+                rovers: vec![0, 1],
+                cell: at(1, 0),
+            }]
+        );
+
+        let result = run("0 0 E\nM\n1 0 W\nM");
+        assert_eq!(positions(&result), vec!["0 0 E", "1 0 W"]);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::Swap);
+        assert_eq!(result.conflicts[0].rovers, vec![0, 1]);
+    }
+
+    #[test]
+    fn conflicts_report_their_tick_and_cascade() {
+        // Rover 1 turns on the spot while rover 0 drives into it on the third tick.
+        let result = run("0 0 E\nRLM\n1 0 N\nLLL");
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].tick, 2);
+        assert_eq!(result.conflicts[0].rovers, vec![0, 1]);
+
+        // Rovers 1 and 2 both want (2, 0), so rover 1 stays put, which blocks rover 0 as well.
+        let result = run("0 0 E\nM\n1 0 E\nM\n3 0 W\nM");
+        assert_eq!(positions(&result), vec!["0 0 E", "1 0 E", "3 0 W"]);
+        let cells: Vec<_> = result.conflicts.iter().map(|c| c.cell).collect();
+        assert_eq!(cells, vec![at(2, 0), at(1, 0)]);
+    }
+
+    #[test]
+    fn waiting_rovers_follow_once_the_way_is_clear() {
+        // Rover 0 waits behind rover 1 while it turns round, then follows it north.
+        let instructions = "0 0 E\nMM\n1 0 N\nLLLLM";
+        let result = run(instructions);
+        assert_eq!(positions(&result), vec!["0 0 E", "1 1 N"]);
+
+        let result = run_with(instructions, Blocked::Wait(DeadlockPolicy::Abort)).unwrap();
+        assert_eq!(positions(&result), vec!["2 0 E", "1 1 N"]);
+        assert_eq!(result.conflicts.len(), 4);
+        assert!(result.deadlocks.is_empty());
+    }
+
+    #[test]
+    fn cyclic_waits_are_resolved_by_policy() {
+        let swap = "0 0 E\nM\n1 0 W\nMRM";
+        assert_eq!(
+            run_with(swap, Blocked::Wait(DeadlockPolicy::Abort)),
+            Err(RoverError::Deadlock {
+                rovers: "[0, 1]".to_string(),
+                tick: 0,
+            })
+        );
+
+        // Rover 1 gives up its M, then turns north and moves out of the way, with rover 0 waiting
+        // for it and then following.
+        let result = run_with(swap, Blocked::Wait(DeadlockPolicy::YieldByPriority)).unwrap();
+        assert_eq!(positions(&result), vec!["1 0 E", "1 1 N"]);
+        assert_eq!(
+            result.deadlocks,
+            vec![Deadlock {
+                tick: 0,
+                kind: DeadlockKind::CyclicWait,
+                rovers: vec![0, 1],
+            }]
+        );
+    }
+
+    #[test]
+    fn repeated_states_are_found() {
+        // Both rovers keep trying to get into (1, 0), so nothing changes from one tick to the next.
+        let result = run_with(
+            "0 0 E\nMM\n2 0 W\nMM",
+            Blocked::Wait(DeadlockPolicy::DropBlocked),
+        )
+        .unwrap();
+        assert_eq!(positions(&result), vec!["0 0 E", "2 0 W"]);
+        let found: Vec<_> = result.deadlocks.iter().map(|d| (d.tick, d.kind)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (0, DeadlockKind::RepeatedState),
+                (1, DeadlockKind::RepeatedState)
+            ]
+        );
+        assert_eq!(result.deadlocks[0].rovers, vec![0, 1]);
+    }
+}
@@ -0,0 +1,256 @@
+//! Plans the instructions to drive a rover from one pose to another, so that they don't have to
+//! be written by hand.
+//!
+//! This is Dijkstra's algorithm over every (cell, direction) the rover could be in. The moves are
+//! made with `RoverInformation`, the same as when the instructions are executed, so a plan always
+//! replays to the target.
+
+use crate::error::RoverError;
+use crate::{Coordinates, Direction, RoverInformation, RoverPosition, Spin};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// The cost of each instruction, which the planner minimises the total of. The default is 1 for
+/// each, which gives the fewest instructions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlanCosts {
+    /// The cost of `L` or `R`.
+    pub turn: u64,
+    /// The cost of `M`.
+    pub forward: u64,
+}
+
+impl Default for PlanCosts {
+    fn default() -> Self {
+        PlanCosts {
+            turn: 1,
+            forward: 1,
+        }
+    }
+}
+
+/// A position as something that can go in the heap, which needs `Ord`.
+pub(crate) type State = (u16, u16, u8);
+
+pub(crate) fn state(position: &RoverPosition) -> State {
+    let direction = match position.facing {
+        Direction::North => 0,
+        Direction::East => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+    };
+    (
+        position.current_location.latitude,
+        position.current_location.longitude,
+        direction,
+    )
+}
+
+/// Returns the cheapest instructions to drive a rover from `start` to `target` on the plateau,
+/// without ever driving onto one of the `obstacles`. Of the plans with the same cost, it returns
+/// one with the fewest instructions.
+///
+/// If no plan reaches the target, e.g. it is walled in, or is itself an obstacle, this fails with
+/// `RoverError::Unreachable`.
+pub fn plan_path(
+    top_right: Coordinates,
+    obstacles: &[Coordinates],
+    start: RoverPosition,
+    target: RoverPosition,
+    costs: &PlanCosts,
+) -> Result<String, RoverError> {
+    RoverInformation::try_new(top_right, start, None)?;
+    let obstacles: HashSet<Coordinates> = obstacles.iter().copied().collect();
+    let unreachable = || RoverError::Unreachable {
+        from: start.to_string(),
+        to: target.to_string(),
+    };
+    // Without this, the search would go over the whole plateau before giving up.
+    let in_bounds = target.current_location.latitude <= top_right.latitude
+        && target.current_location.longitude <= top_right.longitude;
+    if !in_bounds || obstacles.contains(&target.current_location) {
+        return Err(unreachable());
+    }
+    search(
+        top_right,
+        &obstacles,
+        start,
+        |position| *position == target,
+        costs,
+    )
+    .map(|(instructions, _)| instructions)
+    .ok_or_else(unreachable)
+}
+
+/// The cheapest instructions from `start` to the first position which `is_goal`, and that
+/// position, or `None` if there is no way to get to one. `start` must be on the plateau.
+pub(crate) fn search(
+    top_right: Coordinates,
+    obstacles: &HashSet<Coordinates>,
+    start: RoverPosition,
+    is_goal: impl Fn(&RoverPosition) -> bool,
+    costs: &PlanCosts,
+) -> Option<(String, RoverPosition)> {
+    // The cheapest known way to each state: its (cost, instructions), and the state and
+    // instruction it was reached from.
+    let mut best: HashMap<State, (u64, usize)> = HashMap::new();
+    let mut came_from: HashMap<State, (RoverPosition, char)> = HashMap::new();
+    let mut positions: HashMap<State, RoverPosition> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    best.insert(state(&start), (0, 0));
+    positions.insert(state(&start), start);
+    heap.push(Reverse((0, 0, state(&start))));
+
+    while let Some(Reverse((cost, len, current))) = heap.pop() {
+        if best.get(&current).is_some_and(|best| *best < (cost, len)) {
+            continue;
+        }
+        let position = positions[&current];
+        if is_goal(&position) {
+            return Some((replay_path(&came_from, start, position), position));
+        }
+        let rover = |position| RoverInformation {
+            position,
+            top_right_location: top_right,
+            terrain: None,
+        };
+        let mut next = Vec::with_capacity(3);
+        for (spin, instruction) in [(Spin::Left, 'L'), (Spin::Right, 'R')] {
+            let mut rover = rover(position);
+            rover.spin(spin);
+            next.push((rover.position, instruction, costs.turn));
+        }
+        if let Ok(location) = rover(position).next_location()
+            && !obstacles.contains(&location)
+        {
+            let mut moved = position;
+            moved.current_location = location;
+            next.push((moved, 'M', costs.forward));
+        }
+
+        for (next_position, instruction, step_cost) in next {
+            let key = state(&next_position);
+            let candidate = (cost.saturating_add(step_cost), len + 1);
+            if best.get(&key).is_none_or(|best| candidate < *best) {
+                best.insert(key, candidate);
+                came_from.insert(key, (position, instruction));
+                positions.insert(key, next_position);
+                heap.push(Reverse((candidate.0, candidate.1, key)));
+            }
+        }
+    }
+    None
+}
+
+/// Follows `came_from` back from the target to the start, and returns the instructions in order.
+fn replay_path(
+    came_from: &HashMap<State, (RoverPosition, char)>,
+    start: RoverPosition,
+    target: RoverPosition,
+) -> String {
+    let mut instructions = Vec::new();
+    let mut current = target;
+    while current != start {
+        let (previous, instruction) = came_from[&state(&current)];
+        instructions.push(instruction);
+        current = previous;
+    }
+    instructions.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{at, pose};
+    use crate::{RunOptions, return_coordinates, return_timeline};
+
+    /// Plans a path, and checks that running it ends up at the target.
+    fn plan(obstacles: &[Coordinates], start: &str, target: &str, costs: PlanCosts) -> String {
+        let plan = plan_path(at(5, 5), obstacles, pose(start), pose(target), &costs).unwrap();
+        let replayed = return_coordinates("5 5".to_string(), format!("{start}\n{plan}")).unwrap();
+        assert_eq!(replayed, vec![target.to_string()]);
+        plan
+    }
+
+    #[test]
+    fn shortest_path() {
+        assert_eq!(plan(&[], "1 2 N", "1 2 N", PlanCosts::default()), "");
+        assert_eq!(plan(&[], "1 2 N", "1 4 N", PlanCosts::default()), "MM");
+        let plan = plan(&[], "0 0 N", "3 2 S", PlanCosts::default());
+        // 5 moves and 2 turns is the least there can be.
+        assert_eq!(plan.len(), 7);
+    }
+
+    #[test]
+    fn avoids_obstacles() {
+        // A wall across the plateau, with a gap at the east end.
+        let wall: Vec<_> = (0..5).map(|latitude| at(latitude, 3)).collect();
+        let plan = plan(&wall, "0 0 N", "0 5 N", PlanCosts::default());
+        let timeline = return_timeline(
+            "5 5".to_string(),
+            format!("0 0 N\n{plan}"),
+            &RunOptions::default(),
+        )
+        .unwrap();
+        for entry in &timeline {
+            assert!(!wall.contains(&entry.position.current_location), "{plan}");
+        }
+        assert_eq!(timeline.last().unwrap().position, pose("0 5 N"));
+    }
+
+    #[test]
+    fn unreachable_target() {
+        // The target is walled in on every side.
+        let walls = [at(2, 3), at(2, 1), at(1, 2), at(3, 2)];
+        let res = plan_path(
+            at(5, 5),
+            &walls,
+            pose("0 0 N"),
+            pose("2 2 N"),
+            &PlanCosts::default(),
+        );
+        assert_eq!(
+            res,
+            Err(RoverError::Unreachable {
+                from: "0 0 N".to_string(),
+                to: "2 2 N".to_string(),
+            })
+        );
+        let res = plan_path(
+            at(5, 5),
+            &[],
+            pose("0 0 N"),
+            pose("6 0 N"),
+            &PlanCosts::default(),
+        );
+        assert!(matches!(res, Err(RoverError::Unreachable { .. })));
+    }
+
+    #[test]
+    fn impossible_targets_fail_without_searching() {
+        // A plateau far too big to search all of in a test.
+        let top_right = at(10_000, 10_000);
+        let unreachable = |target: &str| {
+            Err(RoverError::Unreachable {
+                from: "0 0 N".to_string(),
+                to: target.to_string(),
+            })
+        };
+        let res = plan_path(
+            top_right,
+            &[],
+            pose("0 0 N"),
+            pose("10001 0 N"),
+            &PlanCosts::default(),
+        );
+        assert_eq!(res, unreachable("10001 0 N"));
+        let res = plan_path(
+            top_right,
+            &[at(500, 500)],
+            pose("0 0 N"),
+            pose("500 500 E"),
+            &PlanCosts::default(),
+        );
+        assert_eq!(res, unreachable("500 500 E"));
+    }
+}
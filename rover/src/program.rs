@@ -0,0 +1,714 @@
+//! The instruction language: plain instructions, with counts and groups for repeating them.
+//!
+//! `12M` is twelve `M`s, and `(MMR)4` is `MMR` four times. A count can go before any single
+//! instruction or group, or after a group, but not both, and groups can be nested, e.g.
+//! `2(M(LR)3)`. A line of plain instructions is just itself, so every mission written before
+//! counts and groups means the same as it did. Anywhere else, a letter which isn't an instruction
+//! is a syntax error, at its offset, the same as a stray bracket.
+//!
+//! Counts multiply, so a short line can stand for a huge number of steps. The expanded length of
+//! each rover's instructions is worked out before any of it is written out, and checked against
+//! `Limits::max_instruction_len`, or `MAX_EXPANDED_STEPS` without one. Groups can only be nested
+//! `MAX_NESTING` deep. Everything written out for a mission, every rover's instructions and every
+//! macro's, also counts towards `Limits::max_total_steps`, or `MAX_EXPANDED_TOTAL` without one,
+//! before it's written, so that lots of rovers can't add up to more than one could on its own.
+//!
+//! Macros are defined on their own lines of the mission, as `def NAME = INSTRUCTIONS`, and called
+//! by name in braces, e.g. `{SQUARE}`, which stands for its instructions, expanded. The braces keep
+//! a name from being read as instructions. A call can have a count, the same as a group, and a
+//! macro can call other macros, but not itself, even through others. Each macro is expanded the
+//! first time it's called, so one which is never called isn't checked beyond its name.
+//!
+//! I keep the parsed groups around after expanding them, so that a failure part way through the
+//! expanded instructions can still be reported at its offset in the line as it was written.
+//! Offsets are counted in characters, from 0.
+
+use crate::Instruction;
+use crate::error::RoverError;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The most steps that one rover's instructions can expand to, when there is no
+/// `Limits::max_instruction_len`.
+pub const MAX_EXPANDED_STEPS: usize = 1 << 24;
+
+/// The most steps that everything in a mission can expand to altogether, when there is no
+/// `Limits::max_total_steps`.
+pub const MAX_EXPANDED_TOTAL: usize = 1 << 26;
+
+/// How deep groups can be nested, and macros can call each other. The parser, and everything
+/// which walks what it parses, recurses once per level, so this keeps a line made of nothing but
+/// `(`s from running out of stack.
+pub const MAX_NESTING: usize = 256;
+
+/// The start of a line which defines a macro, rather than being one of a rover's lines.
+pub(crate) const DEFINITION: &str = "def ";
+
+struct Definition {
+    /// The line of the mission it's defined on, counting from 1.
+    line: usize,
+    body: String,
+}
+
+/// The macros defined in a mission, by name.
+#[derive(Default)]
+pub(crate) struct Macros {
+    definitions: HashMap<String, Definition>,
+    /// The steps each macro expands to, once it's been called.
+    expanded: RefCell<HashMap<String, Rc<str>>>,
+    /// The most steps that can be written out, for rovers and macros together, if there's a limit.
+    max_total: Option<usize>,
+    /// How many steps have been written out so far.
+    written: Cell<usize>,
+}
+
+impl Macros {
+    /// No macros yet, for a mission which can expand to `max_total` steps altogether.
+    pub(crate) fn with_max_total(max_total: usize) -> Macros {
+        Macros {
+            max_total: Some(max_total),
+            ..Default::default()
+        }
+    }
+
+    /// The line the first macro is defined on, if any are.
+    pub(crate) fn first_line(&self) -> Option<usize> {
+        self.definitions
+            .values()
+            .map(|definition| definition.line)
+            .min()
+    }
+
+    /// Makes room to write out `size` more steps, unless that would be more than `max_total`.
+    fn reserve(&self, size: usize) -> Result<(), RoverError> {
+        let written = self.written.get().saturating_add(size);
+        match self.max_total {
+            Some(max) if written > max => Err(RoverError::TooManySteps { max }),
+            _ => {
+                self.written.set(written);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the macro defined on the `line`, from everything after `DEFINITION`.
+    pub(crate) fn define(&mut self, definition: &str, line: usize) -> Result<(), RoverError> {
+        let error = |reason: String| RoverError::InvalidMacroDefinition { line, reason };
+        let Some((name, body)) = definition.split_once('=') else {
+            return Err(error(
+                "it needs an `=` between the name and the instructions".to_string(),
+            ));
+        };
+        let name = name.trim();
+        let is_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_name {
+            return Err(error(format!("`{name}` isn't a valid name for a macro")));
+        }
+        if let Some(existing) = self.definitions.get(name) {
+            return Err(error(format!(
+                "{name} is already defined on line {}",
+                existing.line
+            )));
+        }
+        self.definitions.insert(
+            name.to_string(),
+            Definition {
+                line,
+                body: body.trim().to_string(),
+            },
+        );
+        Ok(())
+    }
+}
+
+pub(crate) enum Node {
+    /// Plain instructions, straight from the source, starting at `offset`.
+    Text { offset: usize, len: usize },
+    /// A group, or a single instruction with a count, repeated `count` times. `size` is the
+    /// number of steps in one repetition of the `body`.
+    Repeat {
+        body: Vec<Node>,
+        count: usize,
+        size: usize,
+    },
+    /// A call to a macro, starting at `offset`, and the `len` steps it expands to.
+    Call {
+        offset: usize,
+        steps: Rc<str>,
+        len: usize,
+    },
+}
+
+impl Node {
+    fn size(&self) -> usize {
+        match self {
+            Node::Text { len, .. } | Node::Call { len, .. } => *len,
+            Node::Repeat { count, size, .. } => size.saturating_mul(*count),
+        }
+    }
+}
+
+fn total_size(nodes: &[Node]) -> usize {
+    nodes.iter().map(Node::size).fold(0, usize::saturating_add)
+}
+
+/// One rover's instructions, with any counts and groups expanded.
+pub(crate) struct Program {
+    /// The instructions to carry out, one character per step.
+    pub(crate) steps: String,
+    nodes: Vec<Node>,
+}
+
+impl Program {
+    /// Instructions which are run exactly as written, without any counts or groups.
+    pub(crate) fn plain(source: &str) -> Program {
+        Program {
+            steps: source.to_string(),
+            nodes: vec![Node::Text {
+                offset: 0,
+                len: source.chars().count(),
+            }],
+        }
+    }
+
+    /// Parses and expands the instructions for the `rover`, which may expand to at most
+    /// `max_steps` steps, calling any of the `macros`.
+    pub(crate) fn parse(
+        source: &str,
+        rover: usize,
+        max_steps: usize,
+        macros: &Macros,
+    ) -> Result<Program, RoverError> {
+        Program::parse_calling(source, rover, max_steps, macros, Vec::new())
+    }
+
+    /// The same as `parse`, from inside the macros which are `calling` it, outermost first.
+    fn parse_calling(
+        source: &str,
+        rover: usize,
+        max_steps: usize,
+        macros: &Macros,
+        calling: Vec<String>,
+    ) -> Result<Program, RoverError> {
+        // Most lines are plain instructions, which don't need parsing, however long they are. Their
+        // letters are checked as they run, as they always were. A macro's are checked here, so
+        // that a bad one is reported at its definition rather than wherever it's called.
+        let special = |c: char| c.is_ascii_digit() || matches!(c, '(' | ')' | '{');
+        let too_long = |size: usize| RoverError::InstructionsTooLong {
+            rover,
+            max: max_steps,
+            got: size,
+        };
+        if calling.is_empty() && !source.contains(special) {
+            let program = Program::plain(source);
+            let size = total_size(&program.nodes);
+            if size > max_steps {
+                return Err(too_long(size));
+            }
+            macros.reserve(size)?;
+            return Ok(program);
+        }
+        let tree = Tree::parse_calling(source, rover, max_steps, macros, calling)?;
+        let size = total_size(&tree.nodes);
+        if size > max_steps {
+            return Err(too_long(size));
+        }
+        macros.reserve(size)?;
+        let mut steps = String::with_capacity(size);
+        write(&tree.nodes, &tree.chars, &mut steps);
+        Ok(Program {
+            steps,
+            nodes: tree.nodes,
+        })
+    }
+
+    /// The offset in the source of the instruction which became the `step`th step.
+    pub(crate) fn offset_of(&self, step: usize) -> usize {
+        offset_in(&self.nodes, step).unwrap_or(step)
+    }
+}
+
+/// One rover's instructions, parsed, but not expanded, so however many steps they stand for.
+pub(crate) struct Tree {
+    /// The source, where the `Text` nodes are.
+    pub(crate) chars: Vec<char>,
+    pub(crate) nodes: Vec<Node>,
+}
+
+impl Tree {
+    /// Parses the instructions for the `rover`, where any macros they call may expand to at most
+    /// `max_steps` steps.
+    pub(crate) fn parse(
+        source: &str,
+        rover: usize,
+        max_steps: usize,
+        macros: &Macros,
+    ) -> Result<Tree, RoverError> {
+        Tree::parse_calling(source, rover, max_steps, macros, Vec::new())
+    }
+
+    fn parse_calling(
+        source: &str,
+        rover: usize,
+        max_steps: usize,
+        macros: &Macros,
+        calling: Vec<String>,
+    ) -> Result<Tree, RoverError> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+            rover,
+            max_steps,
+            macros,
+            calling,
+            depth: 0,
+        };
+        let nodes = parser.sequence()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error(parser.pos, "this `)` has no `(` to close"));
+        }
+        Ok(Tree {
+            chars: parser.chars,
+            nodes,
+        })
+    }
+}
+
+fn write(nodes: &[Node], chars: &[char], steps: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text { offset, len } => steps.extend(&chars[*offset..offset + len]),
+            Node::Repeat { body, count, .. } => {
+                // Written once, then copied, doubling what's there each time, which is much
+                // quicker for a short body with a large count.
+                let start = steps.len();
+                write(body, chars, steps);
+                let total = (steps.len() - start) * count;
+                while steps.len() - start < total {
+                    let written = steps.len() - start;
+                    steps.extend_from_within(start..start + written.min(total - written));
+                }
+            }
+            Node::Call { steps: called, .. } => steps.push_str(called),
+        }
+    }
+}
+
+fn offset_in(nodes: &[Node], mut step: usize) -> Option<usize> {
+    for node in nodes {
+        let size = node.size();
+        if step < size {
+            return match node {
+                Node::Text { offset, .. } => Some(offset + step),
+                // Everything a macro expands to is at the call, as far as the rover's line goes.
+                Node::Call { offset, .. } => Some(*offset),
+                Node::Repeat { body, size, .. } => offset_in(body, step % size),
+            };
+        }
+        step -= size;
+    }
+    None
+}
+
+/// A recursive descent parser, one character at a time.
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    rover: usize,
+    max_steps: usize,
+    macros: &'a Macros,
+    calling: Vec<String>,
+    /// How many groups we're inside.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, offset: usize, reason: &str) -> RoverError {
+        RoverError::InvalidSyntax {
+            rover: self.rover,
+            offset,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Items up to the end of the line, or the `)` which closes the group we're in.
+    fn sequence(&mut self) -> Result<Vec<Node>, RoverError> {
+        let mut nodes: Vec<Node> = Vec::new();
+        while self.peek().is_some_and(|c| c != ')') {
+            let node = self.item()?;
+            // Runs of plain instructions are kept as one node, so a plain line is a single node.
+            if let (
+                Some(Node::Text { offset, len }),
+                Node::Text {
+                    offset: next,
+                    len: 1,
+                },
+            ) = (nodes.last_mut(), &node)
+                && *offset + *len == *next
+            {
+                *len += 1;
+                continue;
+            }
+            nodes.push(node);
+        }
+        Ok(nodes)
+    }
+
+    /// A single instruction or group, with its count if it has one.
+    fn item(&mut self) -> Result<Node, RoverError> {
+        let start = self.pos;
+        let prefix = self.count()?;
+        let node = match self.peek() {
+            None | Some(')') => {
+                return Err(self.error(start, "a count needs an instruction or group after it"));
+            }
+            Some(open @ ('(' | '{')) => {
+                let body = if open == '(' {
+                    self.group()?
+                } else {
+                    vec![self.call()?]
+                };
+                let suffix_start = self.pos;
+                let suffix = self.count()?;
+                if prefix.is_some() && suffix.is_some() {
+                    return Err(self.error(
+                        suffix_start,
+                        "a group can't have a count both before and after it",
+                    ));
+                }
+                Node::Repeat {
+                    size: total_size(&body),
+                    body,
+                    count: prefix.or(suffix).unwrap_or(1),
+                }
+            }
+            Some(c) => {
+                if Instruction::decode(c).is_none() {
+                    return Err(self.error(self.pos, &format!("`{c}` isn't an instruction")));
+                }
+                let text = Node::Text {
+                    offset: self.pos,
+                    len: 1,
+                };
+                self.pos += 1;
+                match prefix {
+                    Some(count) => Node::Repeat {
+                        body: vec![text],
+                        count,
+                        size: 1,
+                    },
+                    None => text,
+                }
+            }
+        };
+        Ok(node)
+    }
+
+    /// The items in a group, up to and past its `)`.
+    fn group(&mut self) -> Result<Vec<Node>, RoverError> {
+        let open = self.pos;
+        if self.depth == MAX_NESTING {
+            let reason = format!("groups can't be nested more than {MAX_NESTING} deep");
+            return Err(self.error(open, &reason));
+        }
+        self.pos += 1;
+        self.depth += 1;
+        let body = self.sequence()?;
+        self.depth -= 1;
+        if self.peek() != Some(')') {
+            return Err(self.error(open, "this `(` is never closed"));
+        }
+        self.pos += 1;
+        Ok(body)
+    }
+
+    /// A call to a macro, up to and past its `}`.
+    fn call(&mut self) -> Result<Node, RoverError> {
+        let open = self.pos;
+        let Some(close) = self.chars[open..].iter().position(|c| *c == '}') else {
+            return Err(self.error(open, "this `{` is never closed"));
+        };
+        let name: String = self.chars[open + 1..open + close].iter().collect();
+        self.pos = open + close + 1;
+        let steps = self.expand(&name, open)?;
+        Ok(Node::Call {
+            offset: open,
+            len: steps.chars().count(),
+            steps,
+        })
+    }
+
+    /// The steps that the macro called `name` expands to, for a call at `offset`.
+    ///
+    /// An error in the macro, or in another macro it calls, is reported at this call, so by the
+    /// time it gets out of the rover's line it's at the call there. The error keeps the name and
+    /// line of the macro which is actually wrong.
+    fn expand(&self, name: &str, offset: usize) -> Result<Rc<str>, RoverError> {
+        let Some(definition) = self.macros.definitions.get(name) else {
+            return Err(self.error(offset, &format!("there is no macro called `{name}`")));
+        };
+        if let Some(steps) = self.macros.expanded.borrow().get(name) {
+            return Ok(steps.clone());
+        }
+        let macro_error = |name: &str, line: usize, reason: String| RoverError::MacroError {
+            rover: self.rover,
+            offset,
+            name: name.to_string(),
+            line,
+            reason,
+        };
+        if let Some(first) = self.calling.iter().position(|c| c == name) {
+            let chain = [&self.calling[first..], &[name.to_string()]]
+                .concat()
+                .join(" -> ");
+            return Err(macro_error(
+                name,
+                definition.line,
+                format!("it calls itself, through {chain}"),
+            ));
+        }
+        if self.calling.len() == MAX_NESTING {
+            return Err(macro_error(
+                name,
+                definition.line,
+                format!("macros can't call each other more than {MAX_NESTING} deep"),
+            ));
+        }
+        let mut calling = self.calling.clone();
+        calling.push(name.to_string());
+        let program = Program::parse_calling(
+            &definition.body,
+            self.rover,
+            self.max_steps,
+            self.macros,
+            calling,
+        )
+        .map_err(|error| match error {
+            RoverError::InvalidSyntax {
+                offset: at, reason, ..
+            } => macro_error(
+                name,
+                definition.line,
+                format!("{reason}, at offset {at} of its definition"),
+            ),
+            RoverError::MacroError {
+                name, line, reason, ..
+            } => macro_error(&name, line, reason),
+            error => error,
+        })?;
+        let steps: Rc<str> = program.steps.into();
+        self.macros
+            .expanded
+            .borrow_mut()
+            .insert(name.to_string(), steps.clone());
+        Ok(steps)
+    }
+
+    /// A count, if there is one here.
+    fn count(&mut self) -> Result<Option<usize>, RoverError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse::<usize>() {
+            Ok(0) => Err(self.error(start, "a count can't be 0")),
+            Ok(count) => Ok(Some(count)),
+            Err(_) => Err(self.error(start, "this count is too large")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Limits, RunOptions, expand_mission, return_coordinates, return_coordinates_with_options,
+    };
+
+    fn expand(source: &str) -> Result<String, RoverError> {
+        Program::parse(source, 0, 100, &Macros::default()).map(|program| program.steps)
+    }
+
+    fn syntax_error(offset: usize, reason: &str) -> Result<String, RoverError> {
+        Err(RoverError::InvalidSyntax {
+            rover: 0,
+            offset,
+            reason: reason.to_string(),
+        })
+    }
+
+    #[test]
+    fn counts_and_groups() {
+        assert_eq!(expand("LMLMLMLMM"), Ok("LMLMLMLMM".to_string()));
+        assert_eq!(expand("12M3R"), Ok("MMMMMMMMMMMMRRR".to_string()));
+        assert_eq!(expand("(MMR)2L"), Ok("MMRMMRL".to_string()));
+        assert_eq!(expand("2(M(LR)2)"), Ok("MLRLRMLRLR".to_string()));
+
+        // The example from the problem, with its four LMs as a group.
+        let res = return_coordinates("5 5".to_string(), "1 2 N\n(LM)4M".to_string());
+        assert_eq!(res, Ok(vec!["1 3 N".to_string()]));
+    }
+
+    #[test]
+    fn syntax_errors_are_at_their_offset() {
+        assert_eq!(expand("MM(LR"), syntax_error(2, "this `(` is never closed"));
+        assert_eq!(
+            expand("MM)"),
+            syntax_error(2, "this `)` has no `(` to close")
+        );
+        assert_eq!(
+            expand("M12"),
+            syntax_error(1, "a count needs an instruction or group after it")
+        );
+        assert_eq!(
+            expand("3M(LX)"),
+            syntax_error(4, "`X` isn't an instruction")
+        );
+        assert_eq!(expand("0M"), syntax_error(0, "a count can't be 0"));
+        assert_eq!(
+            expand("2(M)3"),
+            syntax_error(4, "a group can't have a count both before and after it")
+        );
+    }
+
+    #[test]
+    fn expansion_is_bounded() {
+        assert_eq!(
+            expand("((M)100)100"),
+            Err(RoverError::InstructionsTooLong {
+                rover: 0,
+                max: 100,
+                got: 10_000,
+            })
+        );
+        let options = RunOptions {
+            limits: Limits {
+                max_total_steps: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let res = return_coordinates_with_options(
+            "5 5".to_string(),
+            "0 0 N\n(RL)6".to_string(),
+            &options,
+        );
+        assert_eq!(res, Err(RoverError::TooManySteps { max: 10 }));
+
+        // Every rover on its own is within `MAX_EXPANDED_STEPS`, but not all of them together.
+        let mission = format!("0 0 N\n{MAX_EXPANDED_STEPS}L\n").repeat(20);
+        assert_eq!(
+            expand_mission(mission.trim_end().to_string(), &RunOptions::default()),
+            Err(RoverError::TooManySteps {
+                max: MAX_EXPANDED_TOTAL
+            })
+        );
+    }
+
+    #[test]
+    fn nesting_is_bounded() {
+        let nested = |depth: usize| format!("{}M{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(expand(&nested(MAX_NESTING)), Ok("M".to_string()));
+        assert_eq!(
+            expand(&nested(20_000)),
+            syntax_error(
+                MAX_NESTING,
+                &format!("groups can't be nested more than {MAX_NESTING} deep")
+            )
+        );
+
+        // A chain of macros, each calling the next.
+        let mut mission: String = (0..=MAX_NESTING)
+            .map(|n| format!("def M{n} = {{M{}}}\n", n + 1))
+            .collect();
+        mission.push_str(&format!("def M{} = M\n0 0 N\n{{M0}}", MAX_NESTING + 1));
+        assert!(matches!(
+            expand_with_macros(&mission),
+            Err(RoverError::MacroError { rover: 0, offset: 0, reason, .. })
+                if reason.contains("more than")
+        ));
+    }
+
+    #[test]
+    fn offsets_map_back_to_the_source() {
+        let program = Program::parse("L3(MR)M", 0, 100, &Macros::default()).unwrap();
+        assert_eq!(program.steps, "LMRMRMRM");
+        let offsets: Vec<_> = (0..program.steps.len())
+            .map(|s| program.offset_of(s))
+            .collect();
+        assert_eq!(offsets, vec![0, 3, 4, 3, 4, 3, 4, 6]);
+    }
+
+    fn expand_with_macros(instructions: &str) -> Result<String, RoverError> {
+        expand_mission(instructions.to_string(), &RunOptions::default())
+    }
+
+    #[test]
+    fn macros_expand_where_they_are_called() {
+        let mission =
+            "def SQUARE = 4(MR)\ndef TWICE = {SQUARE}2\n1 1 N\nR{TWICE}L\n1 1 E\n{SQUARE}";
+        assert_eq!(
+            expand_with_macros(mission),
+            Ok("1 1 N\nRMRMRMRMRMRMRMRMRL\n1 1 E\nMRMRMRMR".to_string())
+        );
+        let res = return_coordinates("5 5".to_string(), mission.to_string());
+        assert_eq!(res, Ok(vec!["1 1 N".to_string(), "1 1 E".to_string()]));
+
+        // A failure inside a macro is at the call.
+        let program = Program::parse("L{SQUARE}", 0, 100, &{
+            let mut macros = Macros::default();
+            macros.define("SQUARE = 4(MR)", 1).unwrap();
+            macros
+        })
+        .unwrap();
+        assert_eq!(program.offset_of(5), 1);
+    }
+
+    #[test]
+    fn macro_errors_have_both_sites() {
+        assert_eq!(
+            expand_with_macros("def A = M{B}\ndef B = R{A}\n0 0 N\nMM{A}"),
+            Err(RoverError::MacroError {
+                rover: 0,
+                offset: 2,
+                name: "A".to_string(),
+                line: 1,
+                reason: "it calls itself, through A -> B -> A".to_string(),
+            })
+        );
+        assert_eq!(
+            expand_with_macros("0 0 N\nM\ndef BAD = M(R\n0 0 N\n{BAD}"),
+            Err(RoverError::MacroError {
+                rover: 1,
+                offset: 0,
+                name: "BAD".to_string(),
+                line: 3,
+                reason: "this `(` is never closed, at offset 1 of its definition".to_string(),
+            })
+        );
+        assert_eq!(
+            expand_with_macros("0 0 N\nM{NOPE}"),
+            syntax_error(1, "there is no macro called `NOPE`")
+        );
+        assert_eq!(
+            expand_with_macros("def A = M\ndef A = R\n0 0 N\n{A}"),
+            Err(RoverError::InvalidMacroDefinition {
+                line: 2,
+                reason: "A is already defined on line 1".to_string(),
+            })
+        );
+    }
+}
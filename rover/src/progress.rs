@@ -0,0 +1,200 @@
+//! Cancelling a long mission part way through, and reporting how far it has got.
+
+use crate::error::RoverError;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How many instructions are executed between each check of the cancellation token/progress
+/// report. Checking the token is only an atomic load, but calling the progress callback after
+/// every single instruction would cost more than the instruction itself.
+pub const PROGRESS_INTERVAL: usize = 1024;
+
+/// A flag which can be set from another thread to stop a running mission.
+///
+/// Clones share the same flag, so keep one clone and pass another in to the mission.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every mission holding a clone of this token to stop. A mission stops at its next
+    /// check, which is at most `PROGRESS_INTERVAL` instructions later, and fails with
+    /// `RoverError::Cancelled`.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How far a mission has got.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// Rovers which have finished all of their instructions.
+    pub rovers_completed: usize,
+    /// Instructions executed so far, across all rovers.
+    pub instructions_executed: usize,
+}
+
+/// Keeps count of the progress through a mission, reporting it and checking for cancellation as
+/// it goes.
+pub(crate) struct Tracker<'a> {
+    progress: Progress,
+    /// Ticks gone by, for missions which run one rover after another.
+    ticks: usize,
+    cancellation: Option<&'a CancellationToken>,
+    on_progress: Option<&'a (dyn Fn(Progress) + Sync)>,
+}
+
+impl<'a> Tracker<'a> {
+    pub(crate) fn new(
+        cancellation: Option<&'a CancellationToken>,
+        on_progress: Option<&'a (dyn Fn(Progress) + Sync)>,
+    ) -> Self {
+        Tracker {
+            progress: Progress::default(),
+            ticks: 0,
+            cancellation,
+            on_progress,
+        }
+    }
+
+    fn report(&self) -> Result<(), RoverError> {
+        if let Some(cancellation) = self.cancellation
+            && cancellation.is_cancelled()
+        {
+            return Err(RoverError::Cancelled {
+                rovers_completed: self.progress.rovers_completed,
+                instructions_executed: self.progress.instructions_executed,
+            });
+        }
+        if let Some(on_progress) = self.on_progress {
+            on_progress(self.progress);
+        }
+        Ok(())
+    }
+
+    /// Called before any rover moves, so that a mission which was cancelled before it started
+    /// doesn't do anything.
+    pub(crate) fn start(&self) -> Result<(), RoverError> {
+        self.report()
+    }
+
+    /// Called after every instruction.
+    pub(crate) fn instruction_executed(&mut self) -> Result<(), RoverError> {
+        self.instructions_executed(1)
+    }
+
+    /// Called after `count` instructions executed in one go. This reports at every interval
+    /// along the way, and stops at the first one that finds the mission cancelled, the same as if
+    /// they'd been executed one at a time.
+    pub(crate) fn instructions_executed(&mut self, count: usize) -> Result<(), RoverError> {
+        let target = self.progress.instructions_executed + count;
+        loop {
+            let next =
+                (self.progress.instructions_executed / PROGRESS_INTERVAL + 1) * PROGRESS_INTERVAL;
+            if next > target {
+                self.progress.instructions_executed = target;
+                return Ok(());
+            }
+            self.progress.instructions_executed = next;
+            self.report()?;
+        }
+    }
+
+    /// Moves the clock on by the `ticks` an instruction took, and returns the tick it started on.
+    pub(crate) fn advance(&mut self, ticks: usize) -> usize {
+        let start = self.ticks;
+        self.ticks += ticks;
+        start
+    }
+
+    /// Called after each rover finishes its instructions.
+    pub(crate) fn rover_completed(&mut self) -> Result<(), RoverError> {
+        self.progress.rovers_completed += 1;
+        self.report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RunOptions, return_coordinates_with_options};
+    use std::sync::Mutex;
+
+    /// Two rovers, which spin on the spot for a few intervals' worth of instructions.
+    fn long_mission() -> String {
+        let spins = "LR".repeat(PROGRESS_INTERVAL * 2);
+        format!("1 2 N\n{spins}\n3 3 E\n{spins}")
+    }
+
+    #[test]
+    fn reports_progress() {
+        let reports = Mutex::new(Vec::new());
+        let on_progress = |progress: Progress| reports.lock().unwrap().push(progress);
+        let options = RunOptions {
+            on_progress: Some(&on_progress),
+            ..Default::default()
+        };
+        let res = return_coordinates_with_options("5 5".to_string(), long_mission(), &options);
+        assert_eq!(res, Ok(vec!["1 2 N".to_string(), "3 3 E".to_string()]));
+
+        let reports = reports.into_inner().unwrap();
+        // Once at the start, every interval, and once per rover.
+        assert_eq!(reports.len(), 1 + 8 + 2);
+        assert_eq!(
+            reports.last(),
+            Some(&Progress {
+                rovers_completed: 2,
+                instructions_executed: PROGRESS_INTERVAL * 8,
+            })
+        );
+    }
+
+    #[test]
+    fn cancel_part_way() {
+        let cancellation = CancellationToken::new();
+        // Cancels from within the progress callback, as if another thread had done it then.
+        let on_progress = |progress: Progress| {
+            if progress.instructions_executed >= PROGRESS_INTERVAL * 5 {
+                cancellation.cancel();
+            }
+        };
+        let options = RunOptions {
+            cancellation: Some(cancellation.clone()),
+            on_progress: Some(&on_progress),
+            ..Default::default()
+        };
+        let res = return_coordinates_with_options("5 5".to_string(), long_mission(), &options);
+        assert_eq!(
+            res,
+            Err(RoverError::Cancelled {
+                rovers_completed: 1,
+                instructions_executed: PROGRESS_INTERVAL * 6,
+            })
+        );
+    }
+
+    #[test]
+    fn cancelled_before_start() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let options = RunOptions {
+            cancellation: Some(cancellation),
+            ..Default::default()
+        };
+        let res = return_coordinates_with_options("5 5".to_string(), long_mission(), &options);
+        assert_eq!(
+            res,
+            Err(RoverError::Cancelled {
+                rovers_completed: 0,
+                instructions_executed: 0,
+            })
+        );
+    }
+}
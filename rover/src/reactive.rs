@@ -0,0 +1,676 @@
+//! Reactive programs, which look at where the rover is before deciding what to do next, rather
+//! than being a fixed string of instructions.
+//!
+//! A program is made of words, separated by whitespace:
+//!
+//! - Plain instructions, e.g. `MMR`, carried out as they are.
+//! - `if COND { ... }`, with an optional `else { ... }` after it.
+//! - `until COND { ... }`, which repeats its body until the condition holds, checking it before
+//!   each time round, so `until blocked { M }` drives up to the first thing in the way.
+//! - `toward X Y`, which turns the rover towards the cell at X Y, if it isn't facing a way that
+//!   gets it closer already. It is a single `L`, `R` or `U`, or nothing.
+//!
+//! A `COND` is `blocked`, when the rover can't drive forward, `clear`, when it can, or `at X Y`,
+//! when it's on the cell at X Y. The cell ahead is blocked if it's off the plateau, too steep to
+//! climb, one of the obstacles, or where another rover is: the rovers go one after another, as in
+//! `return_coordinates`, so that's where the rovers before this one finished, or where the ones
+//! after it were deployed.
+//!
+//! Each rover's program is compiled into a list of `Op`s, with the ifs and untils as jumps, which
+//! are then interpreted one at a time. A loop can go round for ever, so every program stops after
+//! a number of steps, where checking a condition is a step the same as an instruction is. Every
+//! step counts towards the mission's progress, so a loop which does nothing but check a condition
+//! can still be cancelled. Blocks can only be nested `MAX_NESTING` deep.
+
+use crate::error::RoverError;
+use crate::events::{self, RoverEvent};
+use crate::program::MAX_NESTING;
+use crate::progress::Tracker;
+use crate::{
+    Coordinates, Direction, Instruction, RoverInformation, RoverPosition, RunOptions, Spin,
+    check_mission, mission_lines,
+};
+use log::Level;
+
+/// Something a reactive program can check about where the rover is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// The rover can't drive forward.
+    Blocked,
+    /// The rover can drive forward.
+    Clear,
+    /// The rover is on this cell.
+    At(Coordinates),
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Blocked => write!(f, "blocked"),
+            Condition::Clear => write!(f, "clear"),
+            Condition::At(cell) => write!(f, "at {cell}"),
+        }
+    }
+}
+
+/// A condition that was checked while running a reactive program, and which way it went.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BranchTrace {
+    pub rover: usize,
+    /// How many steps the rover's program had taken before this one, from 0.
+    pub step: usize,
+    /// Where the `if` or `until` is in the rover's program, in characters from 0.
+    pub offset: usize,
+    pub condition: Condition,
+    pub holds: bool,
+    /// `then` or `else` for an `if`, and `repeat` or `done` for an `until`.
+    pub branch: &'static str,
+    /// Where the rover was when it checked.
+    pub position: RoverPosition,
+}
+
+/// The result of `run_reactive`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReactiveResult {
+    /// The final position of each rover, in the same order as the rovers in the mission.
+    pub positions: Vec<RoverPosition>,
+    /// Every condition checked, for each rover in turn.
+    pub trace: Vec<BranchTrace>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Construct {
+    If,
+    Until,
+}
+
+enum Op {
+    Act(Instruction),
+    Toward(Coordinates),
+    /// Checks the condition, and jumps to `to` if it doesn't hold for an `if` (to the `else`, or
+    /// past the end), or if it does hold for an `until` (past the end of the loop).
+    Test {
+        condition: Condition,
+        construct: Construct,
+        offset: usize,
+        to: usize,
+    },
+    Jump(usize),
+}
+
+/// Compiles one rover's program, reporting mistakes at their offset in the `source`.
+fn compile(source: &str, rover: usize, strict: bool) -> Result<Vec<Op>, RoverError> {
+    let chars: Vec<char> = source.chars().collect();
+    let word_at = |start: usize, end: usize| chars[start..end].iter().collect::<String>();
+    let mut tokens = Vec::new();
+    let mut word: Option<usize> = None;
+    for (offset, &c) in chars.iter().enumerate() {
+        if c.is_whitespace() || c == '{' || c == '}' {
+            if let Some(start) = word.take() {
+                tokens.push((start, word_at(start, offset)));
+            }
+            if !c.is_whitespace() {
+                tokens.push((offset, c.to_string()));
+            }
+        } else if word.is_none() {
+            word = Some(offset);
+        }
+    }
+    if let Some(start) = word {
+        tokens.push((start, word_at(start, chars.len())));
+    }
+    let mut compiler = Compiler {
+        tokens,
+        pos: 0,
+        rover,
+        ops: Vec::new(),
+        end: chars.len(),
+        depth: 0,
+        strict,
+    };
+    compiler.block(None)?;
+    Ok(compiler.ops)
+}
+
+struct Compiler {
+    /// The words and braces of the program, and their offsets.
+    tokens: Vec<(usize, String)>,
+    pos: usize,
+    rover: usize,
+    ops: Vec<Op>,
+    /// The offset of the end of the program, for mistakes there.
+    end: usize,
+    /// How many blocks we're inside.
+    depth: usize,
+    /// Only `L`, `R` and `M` are instructions, as with `RunOptions::strict_alphabet`.
+    strict: bool,
+}
+
+impl Compiler {
+    fn error(&self, offset: usize, reason: &str) -> RoverError {
+        RoverError::InvalidSyntax {
+            rover: self.rover,
+            offset,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn next(&mut self) -> Option<(usize, String)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|(_, word)| word.as_str())
+    }
+
+    /// Statements up to the end of the program, or the `}` which closes the block opened at
+    /// `open`.
+    fn block(&mut self, open: Option<usize>) -> Result<(), RoverError> {
+        loop {
+            let Some((offset, word)) = self.next() else {
+                return match open {
+                    Some(open) => Err(self.error(open, "this `{` is never closed")),
+                    None => Ok(()),
+                };
+            };
+            match word.as_str() {
+                "}" if open.is_some() => return Ok(()),
+                "}" => return Err(self.error(offset, "this `}` has no `{` to close")),
+                "if" => {
+                    let test = self.test(Construct::If, offset)?;
+                    if self.peek() == Some("else") {
+                        self.next();
+                        let jump = self.ops.len();
+                        self.ops.push(Op::Jump(0));
+                        self.jump_here(test);
+                        self.body()?;
+                        self.ops[jump] = Op::Jump(self.ops.len());
+                    } else {
+                        self.jump_here(test);
+                    }
+                }
+                "until" => {
+                    let top = self.ops.len();
+                    let test = self.test(Construct::Until, offset)?;
+                    self.ops.push(Op::Jump(top));
+                    self.jump_here(test);
+                }
+                "toward" => {
+                    let cell = self.cell(offset)?;
+                    self.ops.push(Op::Toward(cell));
+                }
+                _ => {
+                    for (i, c) in word.chars().enumerate() {
+                        let instruction = Instruction::parse(c, self.strict).map_err(|_| {
+                            self.error(offset + i, &format!("`{c}` isn't an instruction"))
+                        })?;
+                        self.ops.push(Op::Act(instruction));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The condition and body of an `if` or `until` at `offset`, compiled after a test which
+    /// jumps nowhere yet. Returns where the test is, to point its jump at the right place later.
+    fn test(&mut self, construct: Construct, offset: usize) -> Result<usize, RoverError> {
+        let condition = match self.next() {
+            Some((_, word)) if word == "blocked" => Condition::Blocked,
+            Some((_, word)) if word == "clear" => Condition::Clear,
+            Some((at, word)) if word == "at" => Condition::At(self.cell(at)?),
+            Some((at, _)) => {
+                return Err(self.error(at, "expected `blocked`, `clear` or `at X Y`"));
+            }
+            None => return Err(self.error(self.end, "expected a condition")),
+        };
+        let test = self.ops.len();
+        self.ops.push(Op::Test {
+            condition,
+            construct,
+            offset,
+            to: 0,
+        });
+        self.body()?;
+        Ok(test)
+    }
+
+    /// A block in braces.
+    fn body(&mut self) -> Result<(), RoverError> {
+        match self.next() {
+            Some((open, word)) if word == "{" => {
+                if self.depth == MAX_NESTING {
+                    let reason = format!("blocks can't be nested more than {MAX_NESTING} deep");
+                    return Err(self.error(open, &reason));
+                }
+                self.depth += 1;
+                self.block(Some(open))?;
+                self.depth -= 1;
+                Ok(())
+            }
+            Some((at, _)) => Err(self.error(at, "expected a `{`")),
+            None => Err(self.error(self.end, "expected a `{`")),
+        }
+    }
+
+    /// The X and Y of a cell, after the word at `offset`.
+    fn cell(&mut self, offset: usize) -> Result<Coordinates, RoverError> {
+        let mut number = || match self.next() {
+            Some((at, word)) => word
+                .parse::<u16>()
+                .map_err(|_| self.error(at, "expected a coordinate")),
+            None => Err(self.error(offset, "expected two coordinates after this")),
+        };
+        Ok(Coordinates {
+            latitude: number()?,
+            longitude: number()?,
+        })
+    }
+
+    /// Points the jump of the test at `test` to the next op.
+    fn jump_here(&mut self, test: usize) {
+        let here = self.ops.len();
+        if let Op::Test { to, .. } = &mut self.ops[test] {
+            *to = here;
+        }
+    }
+}
+
+/// Runs the mission, where each rover's instructions are a reactive program, for at most
+/// `max_steps` steps of each program, without driving onto any of the `obstacles`.
+///
+/// This takes the plateau and rovers' start positions the same as `return_coordinates_with_options`,
+/// with the limits, terrain and strict alphabet in the `options`, but not the energy model or
+/// durations. There's nothing for a program to call a macro from, so a mission with any defined
+/// fails with `RoverError::InvalidMacroDefinition`. Driving into a blocked cell fails with the
+/// same error as it would there, or `RoverError::Crash` for an obstacle or another rover, and a
+/// program still going after `max_steps` steps fails with `RoverError::StepLimit`.
+pub fn run_reactive(
+    top_right: String,
+    instructions: String,
+    obstacles: &[Coordinates],
+    max_steps: usize,
+    options: &RunOptions,
+) -> Result<ReactiveResult, RoverError> {
+    let (top_right, lines) = check_mission(top_right, options, || {
+        let (macros, lines) = mission_lines(&instructions, options)?;
+        match macros.first_line() {
+            Some(line) => Err(RoverError::InvalidMacroDefinition {
+                line,
+                reason: "reactive programs can't use macros".to_string(),
+            }),
+            None => Ok(lines),
+        }
+    })?;
+    let mut positions = Vec::with_capacity(lines.len());
+    let mut programs = Vec::with_capacity(lines.len());
+    for (rover, (position, source)) in lines.into_iter().enumerate() {
+        let position: RoverPosition = position.to_string().try_into()?;
+        RoverInformation::try_new(top_right, position, options.terrain.as_ref())?;
+        positions.push(position);
+        programs.push(compile(source, rover, options.strict_alphabet)?);
+    }
+
+    let mut tracker = Tracker::new(options.cancellation.as_ref(), options.on_progress);
+    tracker.start()?;
+    let mut trace = Vec::new();
+    for (rover, ops) in programs.iter().enumerate() {
+        let mut runner = Runner {
+            rover,
+            info: RoverInformation::try_new(top_right, positions[rover], options.terrain.as_ref())?,
+            obstacles,
+            others: &positions,
+            max_steps,
+            steps: 0,
+        };
+        events::emit(RoverEvent::RoverDeployed {
+            rover,
+            position: runner.info.position.to_string(),
+        });
+        runner.run(ops, &mut tracker, &mut trace)?;
+        let position = runner.info.position;
+        positions[rover] = position;
+        events::emit(RoverEvent::RoverFinished {
+            rover,
+            position: position.to_string(),
+        });
+        tracker.rover_completed()?;
+    }
+    Ok(ReactiveResult { positions, trace })
+}
+
+/// One rover running its program.
+struct Runner<'a> {
+    rover: usize,
+    info: RoverInformation<'a>,
+    obstacles: &'a [Coordinates],
+    /// Where every rover is, where this one's entry is where it was deployed.
+    others: &'a [RoverPosition],
+    max_steps: usize,
+    steps: usize,
+}
+
+impl Runner<'_> {
+    fn run(
+        &mut self,
+        ops: &[Op],
+        tracker: &mut Tracker,
+        trace: &mut Vec<BranchTrace>,
+    ) -> Result<(), RoverError> {
+        let mut next = 0;
+        while let Some(op) = ops.get(next) {
+            next += 1;
+            if let Op::Jump(to) = op {
+                next = *to;
+                continue;
+            }
+            if self.steps == self.max_steps {
+                return Err(RoverError::StepLimit {
+                    rover: self.rover,
+                    max: self.max_steps,
+                });
+            }
+            match op {
+                Op::Act(instruction) => self.act(*instruction)?,
+                Op::Toward(target) => {
+                    if let Some(spin) = self.turn_toward(*target) {
+                        self.act(Instruction::Pivot(spin))?;
+                    }
+                }
+                Op::Test {
+                    condition,
+                    construct,
+                    offset,
+                    to,
+                } => {
+                    let holds = match condition {
+                        Condition::Blocked => self.ahead().is_err(),
+                        Condition::Clear => self.ahead().is_ok(),
+                        Condition::At(cell) => self.info.position.current_location == *cell,
+                    };
+                    let (jump, branch) = match (construct, holds) {
+                        (Construct::If, true) => (false, "then"),
+                        (Construct::If, false) => (true, "else"),
+                        (Construct::Until, true) => (true, "done"),
+                        (Construct::Until, false) => (false, "repeat"),
+                    };
+                    let entry = BranchTrace {
+                        rover: self.rover,
+                        step: self.steps,
+                        offset: *offset,
+                        condition: *condition,
+                        holds,
+                        branch,
+                        position: self.info.position,
+                    };
+                    if events::enabled(Level::Trace) {
+                        events::emit(RoverEvent::BranchTaken {
+                            rover: self.rover,
+                            step: self.steps,
+                            offset: *offset,
+                            condition: condition.to_string(),
+                            branch,
+                        });
+                    }
+                    trace.push(entry);
+                    if jump {
+                        next = *to;
+                    }
+                }
+                Op::Jump(_) => unreachable!("jumps are taken before counting a step"),
+            }
+            self.steps += 1;
+            tracker.instruction_executed()?;
+        }
+        Ok(())
+    }
+
+    fn act(&mut self, instruction: Instruction) -> Result<(), RoverError> {
+        if let Some(heading) = self.info.heading_of(&instruction) {
+            let next = self.towards(heading)?;
+            self.info.position.current_location = next;
+            Ok(())
+        } else {
+            self.info.try_instruction(instruction)
+        }
+    }
+
+    /// The cell ahead of the rover, if it can drive into it.
+    fn ahead(&self) -> Result<Coordinates, RoverError> {
+        self.towards(self.info.position.facing)
+    }
+
+    /// The cell towards `heading`, if the rover can drive into it.
+    fn towards(&self, heading: Direction) -> Result<Coordinates, RoverError> {
+        let next = self.info.check_move(heading)?;
+        let taken =
+            self.obstacles.contains(&next)
+                || self.others.iter().enumerate().any(|(other, position)| {
+                    other != self.rover && position.current_location == next
+                });
+        if taken {
+            Err(RoverError::Crash {
+                rover: self.rover,
+                cell: next.to_string(),
+            })
+        } else {
+            Ok(next)
+        }
+    }
+
+    /// The spin which turns the rover towards the `target`, or `None` if it's already facing a
+    /// way that gets it closer, or is there.
+    fn turn_toward(&self, target: Coordinates) -> Option<Spin> {
+        let here = self.info.position.current_location;
+        let east = i32::from(target.latitude) - i32::from(here.latitude);
+        let north = i32::from(target.longitude) - i32::from(here.longitude);
+        let closer = |direction: Direction| match direction {
+            Direction::North => north > 0,
+            Direction::South => north < 0,
+            Direction::East => east > 0,
+            Direction::West => east < 0,
+        };
+        let facing = self.info.position.facing;
+        if (east, north) == (0, 0) || closer(facing) {
+            return None;
+        }
+        // The way with further to go, so the rover goes straight for longer.
+        let wanted = match (east.abs() >= north.abs(), east > 0, north > 0) {
+            (true, true, _) => Direction::East,
+            (true, false, _) => Direction::West,
+            (false, _, true) => Direction::North,
+            (false, _, false) => Direction::South,
+        };
+        [Spin::Left, Spin::Right, Spin::Around]
+            .into_iter()
+            .find(|spin| {
+                let mut turned = RoverInformation { ..self.info };
+                turned.spin(*spin);
+                turned.position.facing == wanted
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::at;
+    use crate::{CancellationToken, PROGRESS_INTERVAL, Progress};
+
+    fn run(mission: &str, obstacles: &[Coordinates]) -> Result<ReactiveResult, RoverError> {
+        run_reactive(
+            "5 5".to_string(),
+            mission.to_string(),
+            obstacles,
+            100,
+            &RunOptions::default(),
+        )
+    }
+
+    #[test]
+    fn drives_until_blocked() {
+        // Up to the edge, and round the obstacle in the way of the second rover.
+        let result = run(
+            "0 0 N\nuntil blocked { M } R\n2 0 N\nuntil blocked { M } if blocked { R } else { L }",
+            &[at(2, 3)],
+        )
+        .unwrap();
+        assert_eq!(
+            result.positions,
+            vec![
+                "0 5 E".to_string().try_into().unwrap(),
+                "2 2 E".to_string().try_into().unwrap(),
+            ]
+        );
+        let branches: Vec<_> = result.trace.iter().map(|t| (t.rover, t.branch)).collect();
+        assert_eq!(
+            branches,
+            vec![
+                (0, "repeat"),
+                (0, "repeat"),
+                (0, "repeat"),
+                (0, "repeat"),
+                (0, "repeat"),
+                (0, "done"),
+                (1, "repeat"),
+                (1, "repeat"),
+                (1, "done"),
+                (1, "then"),
+            ]
+        );
+        assert_eq!(result.trace[9].offset, 20);
+    }
+
+    #[test]
+    fn turns_toward_its_target() {
+        let result = run(
+            "0 0 S\nuntil at 3 2 { toward 3 2 M }\n5 5 N\ntoward 5 5",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            result.positions,
+            vec![
+                "3 2 N".to_string().try_into().unwrap(),
+                "5 5 N".to_string().try_into().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn programs_always_stop() {
+        assert_eq!(
+            run("0 0 N\nuntil at 9 9 { L }", &[]),
+            Err(RoverError::StepLimit { rover: 0, max: 100 })
+        );
+        // Even without any instructions in the loop.
+        assert_eq!(
+            run("0 0 N\nuntil blocked { }", &[]),
+            Err(RoverError::StepLimit { rover: 0, max: 100 })
+        );
+    }
+
+    #[test]
+    fn checking_conditions_can_be_cancelled() {
+        let cancellation = CancellationToken::new();
+        let on_progress = |progress: Progress| {
+            if progress.instructions_executed >= PROGRESS_INTERVAL * 2 {
+                cancellation.cancel();
+            }
+        };
+        let options = RunOptions {
+            cancellation: Some(cancellation.clone()),
+            on_progress: Some(&on_progress),
+            ..Default::default()
+        };
+        // Never does anything but check whether it's blocked.
+        let res = run_reactive(
+            "5 5".to_string(),
+            "0 0 N\nuntil blocked { }".to_string(),
+            &[],
+            usize::MAX,
+            &options,
+        );
+        assert_eq!(
+            res,
+            Err(RoverError::Cancelled {
+                rovers_completed: 0,
+                instructions_executed: PROGRESS_INTERVAL * 3,
+            })
+        );
+    }
+
+    #[test]
+    fn lines_are_split_the_same_as_a_mission() {
+        let strict = RunOptions {
+            strict_alphabet: true,
+            ..Default::default()
+        };
+        let run_with = |mission: &str, options: &RunOptions| {
+            run_reactive("5 5".to_string(), mission.to_string(), &[], 100, options)
+        };
+        assert!(run_with("0 2 N\nB", &RunOptions::default()).is_ok());
+        assert_eq!(
+            run_with("0 2 N\nB", &strict).err(),
+            Some(RoverError::InvalidSyntax {
+                rover: 0,
+                offset: 0,
+                reason: "`B` isn't an instruction".to_string(),
+            })
+        );
+        // A definition isn't a rover's line, but there's nothing to call it from.
+        assert_eq!(
+            run("def SQUARE = 4(MR)\n0 0 N\nM", &[]).err(),
+            Some(RoverError::InvalidMacroDefinition {
+                line: 1,
+                reason: "reactive programs can't use macros".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn nesting_is_bounded() {
+        let nested =
+            |depth: usize| format!("{}M{}", "if clear { ".repeat(depth), " }".repeat(depth));
+        assert!(compile(&nested(MAX_NESTING), 0, false).is_ok());
+        assert_eq!(
+            compile(&nested(20_000), 0, false).err(),
+            Some(RoverError::InvalidSyntax {
+                rover: 0,
+                offset: MAX_NESTING * 11 + 9,
+                reason: format!("blocks can't be nested more than {MAX_NESTING} deep"),
+            })
+        );
+    }
+
+    #[test]
+    fn mistakes_are_at_their_offset() {
+        let syntax_error = |offset: usize, reason: &str| {
+            Err(RoverError::InvalidSyntax {
+                rover: 0,
+                offset,
+                reason: reason.to_string(),
+            })
+        };
+        assert_eq!(
+            run("0 0 N\nif blocked { M", &[]),
+            syntax_error(11, "this `{` is never closed")
+        );
+        assert_eq!(
+            run("0 0 N\nuntil stuck { M }", &[]),
+            syntax_error(6, "expected `blocked`, `clear` or `at X Y`")
+        );
+        assert_eq!(
+            run("0 0 N\nMMX", &[]),
+            syntax_error(2, "`X` isn't an instruction")
+        );
+        assert_eq!(
+            run("0 0 N\nM\n0 2 S\nM", &[]),
+            Err(RoverError::Crash {
+                rover: 1,
+                cell: "0 1".to_string(),
+            })
+        );
+    }
+}
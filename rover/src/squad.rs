@@ -0,0 +1,229 @@
+//! Plans instructions for a squad of rovers, each driving to its own target at the same time,
+//! without any of them getting in each other's way when they are run with `run_lockstep`.
+//!
+//! This is prioritised planning: the rovers are planned one at a time, in order, and each plan is
+//! added to a space-time reservation table, of the cell each rover is in on every tick, and the
+//! moves it makes between ticks. Each later rover is planned with a breadth-first search over
+//! (pose, tick), which only goes where the table leaves free, so it can't end up in the same cell
+//! as an earlier rover or swap cells with it. Where the quickest way is to let another rover go
+//! past first, the plan waits with `W`. A rover which has reached its target stays there for
+//! good, so it only counts as there once no earlier rover needs that cell again.
+//!
+//! Prioritised planning is quick, but not complete: an earlier rover is planned without any
+//! thought for the later ones, so it can leave a later rover with no way through (e.g. by parking
+//! in a corridor the later rover needs), even if a plan for every rover exists with another order.
+
+use crate::error::RoverError;
+use crate::planner::{State, state};
+use crate::{Coordinates, RoverInformation, RoverPosition, Spin};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Where the rovers that have already been planned are, and when.
+#[derive(Default)]
+struct Reservations {
+    /// Each cell that a rover is in at the end of a tick, and the tick.
+    cells: HashSet<(Coordinates, usize)>,
+    /// Each move from one cell to another during a tick, and the tick.
+    moves: HashSet<(Coordinates, Coordinates, usize)>,
+    /// The cells where rovers have stopped for good, and the tick they got there.
+    parked: HashMap<Coordinates, usize>,
+    /// The last tick that each cell is reserved, which a rover can't stop there before.
+    last_reserved: HashMap<Coordinates, usize>,
+    /// The last tick of anything in the table. After it, nothing changes.
+    horizon: usize,
+}
+
+impl Reservations {
+    fn is_free(&self, cell: Coordinates, tick: usize) -> bool {
+        !self.cells.contains(&(cell, tick)) && self.parked.get(&cell).is_none_or(|t| tick < *t)
+    }
+
+    fn reserve(&mut self, path: &[(RoverPosition, char)], start: RoverPosition) {
+        let mut previous = start.current_location;
+        self.cells.insert((previous, 0));
+        for (tick, (position, _)) in path.iter().enumerate() {
+            let cell = position.current_location;
+            if cell != previous {
+                self.moves.insert((previous, cell, tick));
+            }
+            self.cells.insert((cell, tick + 1));
+            self.last_reserved.insert(cell, tick + 1);
+            previous = cell;
+        }
+        self.parked.insert(previous, path.len());
+        self.horizon = self.horizon.max(path.len());
+    }
+}
+
+/// Plans instructions to drive each rover from its start pose to its target pose, where the
+/// `missions` are (start, target) pairs, without ever driving onto one of the `obstacles`.
+///
+/// The plans are collision-free when run in lockstep with the default `Durations`, in the same
+/// order as the `missions`: no two
+/// rovers ever end a tick in the same cell, or swap cells. Rovers earlier in the `missions` have
+/// priority, so get the quicker plans. If a rover can't be planned a way to its target around the
+/// rovers before it, this fails with `RoverError::Unreachable`.
+pub fn plan_squad(
+    top_right: Coordinates,
+    obstacles: &[Coordinates],
+    missions: &[(RoverPosition, RoverPosition)],
+) -> Result<Vec<String>, RoverError> {
+    for (start, _) in missions {
+        RoverInformation::try_new(top_right, *start, None)?;
+    }
+    let obstacles: HashSet<Coordinates> = obstacles.iter().copied().collect();
+    let mut reservations = Reservations::default();
+    let mut plans = Vec::with_capacity(missions.len());
+    for (start, target) in missions {
+        let path =
+            search(top_right, &obstacles, &reservations, *start, *target).ok_or_else(|| {
+                RoverError::Unreachable {
+                    from: start.to_string(),
+                    to: target.to_string(),
+                }
+            })?;
+        reservations.reserve(&path, *start);
+        plans.push(path.iter().map(|(_, instruction)| instruction).collect());
+    }
+    Ok(plans)
+}
+
+/// The quickest way from `start` to `target` around the `reservations`, as the position after
+/// each instruction and the instruction, or `None` if there isn't one.
+fn search(
+    top_right: Coordinates,
+    obstacles: &HashSet<Coordinates>,
+    reservations: &Reservations,
+    start: RoverPosition,
+    target: RoverPosition,
+) -> Option<Vec<(RoverPosition, char)>> {
+    // After the horizon, the table is the same on every tick, so those ticks are all one as far
+    // as having been somewhere before goes. This is what stops the search when there's no way.
+    let key = |position: &RoverPosition, tick: usize| {
+        (state(position), tick.min(reservations.horizon + 1))
+    };
+    let mut came_from: HashMap<(State, usize), (RoverPosition, usize, char)> = HashMap::new();
+    let mut seen = HashSet::from([key(&start, 0)]);
+    let mut queue = VecDeque::from([(start, 0)]);
+
+    while let Some((position, tick)) = queue.pop_front() {
+        let cell = position.current_location;
+        let stays_free = reservations
+            .last_reserved
+            .get(&cell)
+            .is_none_or(|t| *t <= tick);
+        if position == target && stays_free {
+            return Some(replay(&came_from, key, start, position, tick));
+        }
+        let rover = |position| RoverInformation {
+            position,
+            top_right_location: top_right,
+            terrain: None,
+        };
+        let mut next = Vec::with_capacity(4);
+        for (spin, instruction) in [(Spin::Left, 'L'), (Spin::Right, 'R')] {
+            let mut rover = rover(position);
+            rover.spin(spin);
+            next.push((rover.position, instruction));
+        }
+        if let Ok(location) = rover(position).next_location()
+            && !obstacles.contains(&location)
+            && !reservations.moves.contains(&(location, cell, tick))
+        {
+            let mut moved = position;
+            moved.current_location = location;
+            next.push((moved, 'M'));
+        }
+        next.push((position, 'W'));
+
+        for (next_position, instruction) in next {
+            let next_key = key(&next_position, tick + 1);
+            if reservations.is_free(next_position.current_location, tick + 1)
+                && seen.insert(next_key)
+            {
+                came_from.insert(next_key, (position, tick, instruction));
+                queue.push_back((next_position, tick + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Follows `came_from` back from the target to the start, and returns the path in order.
+fn replay(
+    came_from: &HashMap<(State, usize), (RoverPosition, usize, char)>,
+    key: impl Fn(&RoverPosition, usize) -> (State, usize),
+    start: RoverPosition,
+    target: RoverPosition,
+    tick: usize,
+) -> Vec<(RoverPosition, char)> {
+    let mut path = Vec::with_capacity(tick);
+    let (mut current, mut tick) = (target, tick);
+    while tick > 0 {
+        let (previous, previous_tick, instruction) = came_from[&key(&current, tick)];
+        path.push((current, instruction));
+        (current, tick) = (previous, previous_tick);
+    }
+    debug_assert_eq!(current, start);
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{at, pose};
+    use crate::{Blocked, DeadlockPolicy, RunOptions, run_lockstep};
+
+    /// Plans the missions, and checks that running them in lockstep gets every rover to its
+    /// target without a single conflict.
+    fn plan(top_right: Coordinates, missions: &[(&str, &str)]) -> Vec<String> {
+        let poses: Vec<_> = missions.iter().map(|(s, t)| (pose(s), pose(t))).collect();
+        let plans = plan_squad(top_right, &[], &poses).unwrap();
+        let mission: Vec<String> = missions
+            .iter()
+            .zip(&plans)
+            .map(|((start, _), plan)| format!("{start}\n{plan}"))
+            .collect();
+        let result = run_lockstep(
+            top_right.to_string(),
+            mission.join("\n"),
+            &RunOptions::default(),
+            Blocked::Wait(DeadlockPolicy::Abort),
+        )
+        .unwrap();
+        assert!(result.conflicts.is_empty(), "{:?}", result.conflicts);
+        let targets: Vec<_> = poses.iter().map(|(_, target)| *target).collect();
+        assert_eq!(result.positions, targets);
+        plans
+    }
+
+    #[test]
+    fn rovers_pass_each_other() {
+        // Head on along the bottom row, with only one other row to get past in.
+        let plans = plan(at(4, 1), &[("0 0 E", "4 0 E"), ("4 0 W", "0 0 W")]);
+        assert_eq!(plans[0], "MMMM");
+    }
+
+    #[test]
+    fn waits_for_a_crossing() {
+        // Both paths cross (1, 1) on the first tick, so the second rover lets the first go by.
+        let plans = plan(at(5, 5), &[("0 1 E", "2 1 E"), ("1 0 N", "1 2 N")]);
+        assert_eq!(plans, vec!["MM", "WMM"]);
+    }
+
+    #[test]
+    fn shared_target_is_unreachable() {
+        let missions = [
+            (pose("0 0 E"), pose("2 0 E")),
+            (pose("4 0 W"), pose("2 0 E")),
+        ];
+        assert_eq!(
+            plan_squad(at(5, 5), &[], &missions),
+            Err(RoverError::Unreachable {
+                from: "4 0 W".to_string(),
+                to: "2 0 E".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! The elevation of the plateau, for rovers which can't drive up (or down) a cliff.
+//!
+//! Heightmaps load from a text grid of integers, or from a PGM image, where each grey level is an
+//! elevation. Both are read the way a map is: the first row is the northern edge of the plateau,
+//! and each row runs from West to East, so the first value of the last row is the elevation at
+//! (0, 0).
+
+use crate::Coordinates;
+use crate::error::RoverError;
+use std::path::Path;
+
+/// The elevation of every cell on the plateau.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+    width: usize,
+    height: usize,
+    /// Row by row, from the northern row down, as in the file.
+    elevations: Vec<i32>,
+}
+
+fn invalid(reason: impl Into<String>) -> RoverError {
+    RoverError::InvalidHeightmap {
+        reason: reason.into(),
+    }
+}
+
+impl Heightmap {
+    /// Parses a grid of whitespace-separated integers, one row per line. Blank lines are ignored,
+    /// but every other row must be the same length.
+    pub fn from_text(text: &str) -> Result<Self, RoverError> {
+        let mut width = None;
+        let mut elevations = Vec::new();
+        let rows = text.lines().filter(|line| !line.trim().is_empty());
+        for (row, line) in rows.enumerate() {
+            let before = elevations.len();
+            for token in line.split_whitespace() {
+                let elevation = token
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid elevation {token:?} on row {row}")))?;
+                elevations.push(elevation);
+            }
+            let len = elevations.len() - before;
+            match width {
+                None => width = Some(len),
+                Some(width) if width != len => {
+                    return Err(invalid(format!(
+                        "row {row} has {len} cells, but the first row has {width}"
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+        let width = width.ok_or_else(|| invalid("there are no rows"))?;
+        Ok(Heightmap {
+            width,
+            height: elevations.len() / width,
+            elevations,
+        })
+    }
+
+    /// Parses a PGM image, either plain (`P2`) or binary (`P5`), with up to 16 bits per pixel.
+    pub fn from_pgm(bytes: &[u8]) -> Result<Self, RoverError> {
+        let binary = match bytes.get(..2) {
+            Some(b"P2") => false,
+            Some(b"P5") => true,
+            _ => return Err(invalid("not a PGM image, which starts with P2 or P5")),
+        };
+        let mut pos = 2;
+        let width = pgm_header_value(bytes, &mut pos, "width")?;
+        let height = pgm_header_value(bytes, &mut pos, "height")?;
+        let maxval = pgm_header_value(bytes, &mut pos, "maxval")?;
+        if width == 0 || height == 0 {
+            return Err(invalid("PGM image is empty"));
+        }
+        if maxval == 0 || maxval > u16::MAX as usize {
+            return Err(invalid(format!("PGM maxval {maxval} is not 1-65535")));
+        }
+        let cells = width
+            .checked_mul(height)
+            .ok_or_else(|| invalid("PGM image is too large"))?;
+
+        let elevations: Vec<i32> = if binary {
+            // Exactly one whitespace byte separates the header from the raster.
+            let raster = bytes.get(pos + 1..).unwrap_or_default();
+            let sample_len = if maxval < 256 { 1 } else { 2 };
+            let raster_len = cells
+                .checked_mul(sample_len)
+                .ok_or_else(|| invalid("PGM image is too large"))?;
+            if raster.len() < raster_len {
+                return Err(invalid("PGM raster is shorter than the image"));
+            }
+            raster
+                .chunks_exact(sample_len)
+                .take(cells)
+                .map(|sample| match sample {
+                    [value] => *value as i32,
+                    [high, low] => u16::from_be_bytes([*high, *low]) as i32,
+                    _ => unreachable!("samples are one or two bytes"),
+                })
+                .collect()
+        } else {
+            let raster = std::str::from_utf8(&bytes[pos..])
+                .map_err(|_| invalid("plain PGM raster is not text"))?;
+            raster
+                .split_ascii_whitespace()
+                .map(|token| {
+                    token
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid PGM value {token:?}")))
+                })
+                .collect::<Result<_, _>>()?
+        };
+        if elevations.len() != cells {
+            return Err(invalid(format!(
+                "PGM image has {} values, but should have {cells}",
+                elevations.len()
+            )));
+        }
+        if let Some(value) = elevations
+            .iter()
+            .find(|value| !(0..=maxval as i32).contains(*value))
+        {
+            return Err(invalid(format!("PGM value {value} is not 0-{maxval}")));
+        }
+        Ok(Heightmap {
+            width,
+            height,
+            elevations,
+        })
+    }
+
+    /// Loads a heightmap from a file, which is read as a PGM image if it starts with `P2` or `P5`,
+    /// and as a text grid otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RoverError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| invalid(format!("could not read {}: {e}", path.display())))?;
+        if bytes.starts_with(b"P2") || bytes.starts_with(b"P5") {
+            Self::from_pgm(&bytes)
+        } else {
+            let text = String::from_utf8(bytes)
+                .map_err(|_| invalid(format!("{} is not text or PGM", path.display())))?;
+            Self::from_text(&text)
+        }
+    }
+
+    /// The number of cells from West to East.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of cells from South to North.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The elevation at `at`, or `None` if it is off the heightmap.
+    pub fn elevation(&self, at: Coordinates) -> Option<i32> {
+        let (x, y) = (at.latitude as usize, at.longitude as usize);
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let row = self.height - 1 - y;
+        Some(self.elevations[row * self.width + x])
+    }
+
+    /// The heightmap must be exactly the size of the plateau, so that every cell has an
+    /// elevation, and there are no cells which a rover could never reach.
+    pub(crate) fn check_covers(&self, top_right: Coordinates) -> Result<(), RoverError> {
+        let width = top_right.latitude as usize + 1;
+        let height = top_right.longitude as usize + 1;
+        if (self.width, self.height) == (width, height) {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "heightmap is {}x{}, but the plateau is {width}x{height}",
+                self.width, self.height
+            )))
+        }
+    }
+}
+
+/// Reads one of the numbers in a PGM header, skipping any whitespace and comments before it.
+fn pgm_header_value(bytes: &[u8], pos: &mut usize, which: &str) -> Result<usize, RoverError> {
+    loop {
+        match bytes.get(*pos) {
+            Some(b) if b.is_ascii_whitespace() => *pos += 1,
+            Some(b'#') => {
+                while bytes.get(*pos).is_some_and(|b| *b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    let start = *pos;
+    while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&bytes[start..*pos])
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .ok_or_else(|| invalid(format!("missing or invalid PGM {which}")))
+}
+
+/// The plateau's heightmap, and the steepest slope the rovers can drive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Terrain {
+    pub heightmap: Heightmap,
+    /// The largest change in elevation, up or down, between two neighbouring cells that a rover
+    /// can drive. Steep descents are as much of a risk as steep climbs. `None` means any slope.
+    pub max_gradient: Option<u64>,
+}
+
+impl Terrain {
+    /// The change in elevation from `from` to `to`, which are both on the plateau, so are both on
+    /// the heightmap by `Heightmap::check_covers`.
+    fn gradient(&self, from: Coordinates, to: Coordinates) -> i64 {
+        let elevation = |at| self.heightmap.elevation(at).unwrap_or_default() as i64;
+        elevation(to) - elevation(from)
+    }
+
+    /// How far up a move from `from` to `to` climbs, or 0 if it is flat or downhill.
+    pub(crate) fn climb(&self, from: Coordinates, to: Coordinates) -> u64 {
+        self.gradient(from, to).max(0) as u64
+    }
+
+    pub(crate) fn check_slope(&self, from: Coordinates, to: Coordinates) -> Result<(), RoverError> {
+        let gradient = self.gradient(from, to);
+        match self.max_gradient {
+            Some(max) if gradient.unsigned_abs() > max => Err(RoverError::TooSteep {
+                from: from.to_string(),
+                to: to.to_string(),
+                gradient,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EnergyModel, RunOptions, return_coordinates_with_options};
+
+    // A 3x3 plateau with a hill in the middle. (0, 0) is the bottom left, at 0.
+    const HILL: &str = "1 2 1\n2 9 2\n0 2 1\n";
+
+    fn run(instructions: &str, options: RunOptions) -> Result<Vec<String>, RoverError> {
+        return_coordinates_with_options("2 2".to_string(), instructions.to_string(), &options)
+    }
+
+    fn terrain(max_gradient: Option<u64>) -> Option<Terrain> {
+        Some(Terrain {
+            heightmap: Heightmap::from_text(HILL).unwrap(),
+            max_gradient,
+        })
+    }
+
+    #[test]
+    fn text_and_pgm_agree() {
+        let text = Heightmap::from_text(HILL).unwrap();
+        let plain = Heightmap::from_pgm(b"P2\n# a hill\n3 3\n9\n1 2 1\n2 9 2\n0 2 1\n").unwrap();
+        let mut binary = b"P5 3 3 255\n".to_vec();
+        binary.extend([1, 2, 1, 2, 9, 2, 0, 2, 1]);
+        let binary = Heightmap::from_pgm(&binary).unwrap();
+        assert_eq!(text, plain);
+        assert_eq!(text, binary);
+
+        let at = |latitude, longitude| {
+            text.elevation(Coordinates {
+                latitude,
+                longitude,
+            })
+        };
+        assert_eq!(at(0, 0), Some(0));
+        assert_eq!(at(1, 1), Some(9));
+        assert_eq!(at(2, 2), Some(1));
+        assert_eq!(at(3, 0), None);
+    }
+
+    #[test]
+    fn invalid_heightmaps() {
+        assert!(matches!(
+            Heightmap::from_text("1 2\n3"),
+            Err(RoverError::InvalidHeightmap { .. })
+        ));
+        assert!(matches!(
+            Heightmap::from_pgm(b"P5 2 2 255\n\x01\x02\x03"),
+            Err(RoverError::InvalidHeightmap { .. })
+        ));
+        let pgm_error = |reason: &str| {
+            Err(RoverError::InvalidHeightmap {
+                reason: reason.to_string(),
+            })
+        };
+        assert_eq!(
+            Heightmap::from_pgm(b"P5 2 1 100\n\x01\xff"),
+            pgm_error("PGM value 255 is not 0-100")
+        );
+        assert_eq!(
+            Heightmap::from_pgm(b"P2 2 1 9\n3 -1"),
+            pgm_error("PGM value -1 is not 0-9")
+        );
+        let huge = format!("P5 {} 1 65535\n", usize::MAX / 2 + 1);
+        assert_eq!(
+            Heightmap::from_pgm(huge.as_bytes()),
+            pgm_error("PGM image is too large")
+        );
+        // A heightmap must be the same size as the plateau.
+        let options = RunOptions {
+            terrain: terrain(None),
+            ..Default::default()
+        };
+        let res =
+            return_coordinates_with_options("3 3".to_string(), "0 0 N\nM".to_string(), &options);
+        assert_eq!(
+            res,
+            Err(RoverError::InvalidHeightmap {
+                reason: "heightmap is 3x3, but the plateau is 4x4".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn too_steep_reports_gradient() {
+        let options = RunOptions {
+            terrain: terrain(Some(2)),
+            ..Default::default()
+        };
+        // Around the hill is fine, but not over it.
+        assert_eq!(run("0 0 N\nMMRMM", options), Ok(vec!["2 2 E".to_string()]));
+        let options = RunOptions {
+            terrain: terrain(Some(2)),
+            ..Default::default()
+        };
+        assert_eq!(
+            run("0 1 E\nMM", options),
+            Err(RoverError::TooSteep {
+                from: "0 1".to_string(),
+                to: "1 1".to_string(),
+                gradient: 7,
+                max: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn climbing_costs_energy() {
+        let energy = EnergyModel {
+            budget: 4,
+            move_cost: 1,
+            climb_cost: 1,
+            ..Default::default()
+        };
+        // Up 2 (costs 3), then down 1 (costs 1).
+        let options = RunOptions {
+            energy: Some(energy.clone()),
+            terrain: terrain(None),
+            ..Default::default()
+        };
+        assert_eq!(run("0 0 N\nMM", options), Ok(vec!["0 2 N".to_string()]));
+        // Up 7 from (1, 0) to the top of the hill costs 8, which is more than the battery holds.
+        let options = RunOptions {
+            energy: Some(energy),
+            terrain: terrain(None),
+            ..Default::default()
+        };
+        assert!(matches!(
+            run("1 0 N\nM", options),
+            Err(RoverError::OutOfEnergy { .. })
+        ));
+    }
+}
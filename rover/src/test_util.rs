@@ -0,0 +1,15 @@
+//! Shorthand for the positions and cells that come up in tests all over the crate.
+
+use crate::{Coordinates, RoverPosition};
+
+/// A position written the same as in a mission, e.g. `"1 2 N"`.
+pub(crate) fn pose(position: &str) -> RoverPosition {
+    position.to_string().try_into().unwrap()
+}
+
+pub(crate) fn at(latitude: u16, longitude: u16) -> Coordinates {
+    Coordinates {
+        latitude,
+        longitude,
+    }
+}
@@ -0,0 +1,137 @@
+//! How many ticks each instruction takes, and the timeline of when each rover carried out each
+//! of its instructions.
+//!
+//! Ticks only matter once rovers share the plateau at the same time. In the original, sequential
+//! mission, each rover starts on the tick that the one before it finishes, so the timeline is
+//! just the instructions laid end to end. In lockstep, they decide who gets where first.
+
+use crate::error::RoverError;
+use crate::{Instruction, RoverPosition, RunOptions, Spin, run_mission};
+
+/// How many ticks each kind of instruction takes. The default is one tick for each, which is
+/// what lockstep was before durations. A duration of 0 counts as 1.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Durations {
+    /// Ticks for `L` or `R`. `U` takes twice this.
+    pub turn: u32,
+    /// Ticks for `M` or `B`.
+    pub forward: u32,
+    /// Ticks for `W`.
+    pub wait: u32,
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Durations {
+            turn: 1,
+            forward: 1,
+            wait: 1,
+        }
+    }
+}
+
+impl Durations {
+    pub(crate) fn of(&self, instruction: &Instruction) -> usize {
+        let ticks = match instruction {
+            Instruction::Forward | Instruction::Backward => self.forward,
+            Instruction::Pivot(Spin::Around) => self.turn.saturating_mul(2),
+            Instruction::Pivot(_) => self.turn,
+            Instruction::Wait => self.wait,
+        };
+        ticks.max(1) as usize
+    }
+}
+
+/// One instruction carried out by one rover.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineEntry {
+    pub rover: usize,
+    /// The index of the instruction within the rover's instructions.
+    pub step: usize,
+    pub instruction: char,
+    /// The tick the rover started on the instruction.
+    pub start: usize,
+    /// The tick the instruction was finished by. In lockstep, this can be more than `start` plus
+    /// the duration, if the rover had to wait for the way to be clear.
+    pub end: usize,
+    /// Where the rover was once the instruction was finished.
+    pub position: RoverPosition,
+}
+
+/// Runs the mission one rover after another, the same as `return_coordinates_with_options`, and
+/// returns every instruction that was carried out, in order, with the ticks it took.
+pub fn return_timeline(
+    top_right: String,
+    instructions: String,
+    options: &RunOptions,
+) -> Result<Vec<TimelineEntry>, RoverError> {
+    let mut timeline = Vec::new();
+    run_mission(
+        top_right,
+        instructions,
+        options,
+        Some(&mut |_, _, entry| {
+            timeline.extend(entry.cloned());
+        }),
+    )?;
+    Ok(timeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Blocked, run_lockstep};
+
+    fn durations() -> RunOptions<'static> {
+        RunOptions {
+            durations: Durations {
+                turn: 2,
+                forward: 3,
+                wait: 1,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sequential_rovers_follow_on() {
+        let timeline = return_timeline(
+            "5 5".to_string(),
+            "0 0 N\nMW\n1 1 E\nL".to_string(),
+            &durations(),
+        )
+        .unwrap();
+        let ticks: Vec<_> = timeline
+            .iter()
+            .map(|entry| (entry.rover, entry.instruction, entry.start, entry.end))
+            .collect();
+        assert_eq!(ticks, vec![(0, 'M', 0, 3), (0, 'W', 3, 4), (1, 'L', 4, 6)]);
+        assert_eq!(timeline[2].position.to_string(), "1 1 N");
+    }
+
+    #[test]
+    fn lockstep_moves_land_on_their_last_tick() {
+        let run = |instructions: &str| {
+            run_lockstep(
+                "5 5".to_string(),
+                instructions.to_string(),
+                &durations(),
+                Blocked::Skip,
+            )
+            .unwrap()
+        };
+        // Moves take three ticks, so the two rovers only conflict on the tick they'd arrive.
+        let result = run("0 0 E\nM\n2 0 W\nM");
+        assert_eq!(result.conflicts[0].tick, 2);
+        assert!(result.timeline.is_empty());
+
+        let result = run("0 0 E\nWM\n1 0 E\nM");
+        assert!(result.conflicts.is_empty());
+        let ticks: Vec<_> = result
+            .timeline
+            .iter()
+            .map(|entry| (entry.rover, entry.start, entry.end))
+            .collect();
+        assert_eq!(ticks, vec![(0, 0, 1), (1, 0, 3), (0, 1, 4)]);
+    }
+}
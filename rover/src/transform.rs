@@ -0,0 +1,366 @@
+//! The net effect of a rover's instructions, worked out without a plateau or a start position.
+//!
+//! Away from the edges of the plateau, the same instructions always move a rover the same way
+//! relative to where it starts and the way it's facing: it ends up some cells ahead and to the
+//! right, turned some number of quarter turns. `net_transform` works that out, along with the box
+//! around every cell the rover drives through on the way. Then whether the rover stays on the
+//! plateau from a given start, and where it ends up if it does, is a few sums.
+//!
+//! The transform of some instructions followed by some more is the first one's, then the second's
+//! turned and moved to where the first one left the rover. A group is its body's transform, that
+//! many times over, but the body's turn means the rover is facing the same way again after at most
+//! four times round it. Every four times round moves the rover the same amount, in a straight
+//! line, so a group with a huge count is worked out in the same few sums as one without, and
+//! counts never have to be expanded.
+//!
+//! None of this takes terrain or energy into account, either of which can stop a rover part way.
+
+use crate::bytecode::Op;
+use crate::error::RoverError;
+use crate::program::{MAX_EXPANDED_STEPS, Macros, Node, Tree};
+use crate::{Coordinates, Direction, Instruction, RoverPosition, Spin};
+
+/// A cell relative to where a rover starts, and the way it starts facing.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Offset {
+    /// Cells ahead of the start. Negative is behind it.
+    pub ahead: i64,
+    /// Cells to the right of the start. Negative is to the left.
+    pub right: i64,
+}
+
+impl Offset {
+    /// The offset, from a rover which has turned `quarter_turns` to the right from the start.
+    fn turned(self, quarter_turns: u8) -> Offset {
+        let Offset { ahead, right } = self;
+        let (ahead, right) = match quarter_turns % 4 {
+            0 => (ahead, right),
+            1 => (-right, ahead),
+            2 => (-ahead, -right),
+            _ => (right, -ahead),
+        };
+        Offset { ahead, right }
+    }
+
+    fn plus(self, other: Offset) -> Offset {
+        Offset {
+            ahead: self.ahead.saturating_add(other.ahead),
+            right: self.right.saturating_add(other.right),
+        }
+    }
+
+    fn least(self, other: Offset) -> Offset {
+        Offset {
+            ahead: self.ahead.min(other.ahead),
+            right: self.right.min(other.right),
+        }
+    }
+
+    fn most(self, other: Offset) -> Offset {
+        Offset {
+            ahead: self.ahead.max(other.ahead),
+            right: self.right.max(other.right),
+        }
+    }
+
+    fn times(self, count: i64) -> Offset {
+        Offset {
+            ahead: self.ahead.saturating_mul(count),
+            right: self.right.saturating_mul(count),
+        }
+    }
+
+    /// The offset as (east, north), for a rover which starts `facing` that way.
+    fn absolute(self, facing: Direction) -> (i64, i64) {
+        match facing {
+            Direction::North => (self.right, self.ahead),
+            Direction::East => (self.ahead, -self.right),
+            Direction::South => (-self.right, -self.ahead),
+            Direction::West => (-self.ahead, self.right),
+        }
+    }
+}
+
+/// Where some instructions take a rover, relative to its start, if it doesn't reach the edge of
+/// the plateau.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetTransform {
+    /// Quarter turns to the right, from 0 to 3, from the way the rover starts facing to the way
+    /// it ends up facing.
+    pub quarter_turns: u8,
+    /// Where the rover ends up.
+    pub end: Offset,
+    /// The nearest corner of the smallest box which holds every cell the rover is in along the
+    /// way, including where it starts.
+    pub min: Offset,
+    /// The furthest corner of the same box.
+    pub max: Offset,
+}
+
+impl NetTransform {
+    /// The transform of compiled instructions, in one pass over them.
+    pub(crate) fn of(ops: &[Op]) -> NetTransform {
+        let mut transform = NetTransform::default();
+        for op in ops {
+            let count = i64::from(op.count);
+            let turns = match op.instruction {
+                Instruction::Pivot(Spin::Right) => 1,
+                Instruction::Pivot(Spin::Around) => 2,
+                Instruction::Pivot(Spin::Left) => 3,
+                _ => 0,
+            };
+            let quarter_turns = i64::from(transform.quarter_turns) + turns * (count % 4);
+            transform.quarter_turns = (quarter_turns % 4) as u8;
+            let heading = match op.instruction {
+                Instruction::Forward => transform.quarter_turns,
+                Instruction::Backward => (transform.quarter_turns + 2) % 4,
+                _ => continue,
+            };
+            let end = &mut transform.end;
+            match heading {
+                0 => end.ahead += count,
+                1 => end.right += count,
+                2 => end.ahead -= count,
+                _ => end.right -= count,
+            }
+            // A run is in a straight line, so it's only ever furthest out at its end.
+            transform.min = transform.min.least(*end);
+            transform.max = transform.max.most(*end);
+        }
+        transform
+    }
+
+    /// The transform of a single instruction.
+    fn step(instruction: Instruction) -> NetTransform {
+        let (quarter_turns, ahead) = match instruction {
+            Instruction::Pivot(Spin::Right) => (1, 0),
+            Instruction::Pivot(Spin::Around) => (2, 0),
+            Instruction::Pivot(Spin::Left) => (3, 0),
+            Instruction::Forward => (0, 1),
+            Instruction::Backward => (0, -1),
+            Instruction::Wait => (0, 0),
+        };
+        let end = Offset { ahead, right: 0 };
+        NetTransform {
+            quarter_turns,
+            end,
+            min: Offset {
+                ahead: ahead.min(0),
+                right: 0,
+            },
+            max: Offset {
+                ahead: ahead.max(0),
+                right: 0,
+            },
+        }
+    }
+
+    /// This transform, followed by the `next` one, from wherever this one leaves the rover.
+    fn then(self, next: NetTransform) -> NetTransform {
+        // Turning a box turns its corners, but they may not be its min and max corners any more.
+        let corner_1 = self.end.plus(next.min.turned(self.quarter_turns));
+        let corner_2 = self.end.plus(next.max.turned(self.quarter_turns));
+        NetTransform {
+            quarter_turns: (self.quarter_turns + next.quarter_turns) % 4,
+            end: self.end.plus(next.end.turned(self.quarter_turns)),
+            min: self.min.least(corner_1).least(corner_2),
+            max: self.max.most(corner_1).most(corner_2),
+        }
+    }
+
+    /// This transform, `count` times over.
+    fn repeat(self, count: u64) -> NetTransform {
+        let times = |count: u64| (0..count).fold(NetTransform::default(), |t, _| t.then(self));
+        // How many times round before the rover faces the same way again.
+        let period = match self.quarter_turns {
+            0 => 1,
+            2 => 2,
+            _ => 4,
+        };
+        let (rounds, rest) = (count / period, count % period);
+        if rounds == 0 {
+            return times(rest);
+        }
+        // Each round moves the box along by the same amount, so the box around all of them is
+        // from the first one to the last one.
+        let round = times(period);
+        let last = round
+            .end
+            .times(i64::try_from(rounds - 1).unwrap_or(i64::MAX));
+        let rounds = NetTransform {
+            quarter_turns: 0,
+            end: round.end.plus(last),
+            min: round.min.least(round.min.plus(last)),
+            max: round.max.most(round.max.plus(last)),
+        };
+        rounds.then(times(rest))
+    }
+
+    /// The transform of instructions one step at a time.
+    fn of_steps(mut steps: impl Iterator<Item = char>) -> Result<NetTransform, RoverError> {
+        steps.try_fold(NetTransform::default(), |transform, c| {
+            let instruction =
+                Instruction::decode(c).ok_or(RoverError::InstructionError { received: c })?;
+            Ok(transform.then(NetTransform::step(instruction)))
+        })
+    }
+
+    /// The transform of parsed `nodes`, from the `chars` of their source.
+    fn of_nodes(nodes: &[Node], chars: &[char]) -> Result<NetTransform, RoverError> {
+        nodes
+            .iter()
+            .try_fold(NetTransform::default(), |transform, node| {
+                let next = match node {
+                    Node::Text { offset, len } => {
+                        NetTransform::of_steps(chars[*offset..offset + len].iter().copied())?
+                    }
+                    Node::Call { steps, .. } => NetTransform::of_steps(steps.chars())?,
+                    Node::Repeat { body, count, .. } => {
+                        NetTransform::of_nodes(body, chars)?.repeat(*count as u64)
+                    }
+                };
+                Ok(transform.then(next))
+            })
+    }
+
+    /// Whether a rover starting at `start` stays on the plateau all the way.
+    pub fn fits(&self, start: RoverPosition, top_right: Coordinates) -> bool {
+        let (east_1, north_1) = self.min.absolute(start.facing);
+        let (east_2, north_2) = self.max.absolute(start.facing);
+        let x = i64::from(start.current_location.latitude);
+        let y = i64::from(start.current_location.longitude);
+        // Saturating, as a box from a huge count can be nearly as big as an i64.
+        x.saturating_add(east_1.min(east_2)) >= 0
+            && x.saturating_add(east_1.max(east_2)) <= i64::from(top_right.latitude)
+            && y.saturating_add(north_1.min(north_2)) >= 0
+            && y.saturating_add(north_1.max(north_2)) <= i64::from(top_right.longitude)
+    }
+
+    /// Where a rover starting at `start` ends up, or `None` if it would leave the plateau on the
+    /// way.
+    pub fn apply(&self, start: RoverPosition, top_right: Coordinates) -> Option<RoverPosition> {
+        if !self.fits(start, top_right) {
+            return None;
+        }
+        let (east, north) = self.end.absolute(start.facing);
+        let mut facing = start.facing;
+        for _ in 0..self.quarter_turns {
+            facing = match facing {
+                Direction::North => Direction::East,
+                Direction::East => Direction::South,
+                Direction::South => Direction::West,
+                Direction::West => Direction::North,
+            };
+        }
+        // Both fit in a u16, because the end is in the box, which is on the plateau.
+        Some(RoverPosition {
+            current_location: Coordinates {
+                latitude: (i64::from(start.current_location.latitude) + east) as u16,
+                longitude: (i64::from(start.current_location.longitude) + north) as u16,
+            },
+            facing,
+        })
+    }
+}
+
+/// Works out the net transform of a rover's `instructions`, which can have counts and groups,
+/// but not macros, as they're defined in a mission. Counts aren't expanded, so there's no limit on
+/// how many steps the instructions can stand for.
+///
+/// This fails if the instructions do, before any of them would be run, with
+/// `RoverError::InvalidSyntax`, which is also what anything that isn't an instruction gives.
+pub fn net_transform(instructions: &str) -> Result<NetTransform, RoverError> {
+    let tree = Tree::parse(instructions, 0, MAX_EXPANDED_STEPS, &Macros::default())?;
+    NetTransform::of_nodes(&tree.nodes, &tree.chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::{Bytecode, interpret_chars};
+    use crate::program::Program;
+    use crate::test_util::pose;
+
+    #[test]
+    fn transform_is_relative_to_the_start() {
+        let transform = net_transform("MMRMMRMRRM").unwrap();
+        assert_eq!(
+            transform,
+            NetTransform {
+                quarter_turns: 0,
+                end: Offset { ahead: 2, right: 2 },
+                min: Offset { ahead: 0, right: 0 },
+                max: Offset { ahead: 2, right: 2 },
+            }
+        );
+        // The same instructions, from any start, however it's facing.
+        let top_right = Coordinates {
+            latitude: 9,
+            longitude: 9,
+        };
+        for start in ["3 3 N", "3 3 E", "4 5 S", "5 4 W"] {
+            let expected = interpret_chars(top_right, pose(start), "MMRMMRMRRM").unwrap();
+            assert_eq!(transform.apply(pose(start), top_right), Some(expected));
+        }
+    }
+
+    #[test]
+    fn box_covers_the_whole_path() {
+        // Out and back, so it ends where it started, but goes 3 cells behind on the way.
+        let transform = net_transform("3B3M(LR)5").unwrap();
+        assert_eq!(transform.end, Offset::default());
+        assert_eq!(
+            transform.min,
+            Offset {
+                ahead: -3,
+                right: 0
+            }
+        );
+        let top_right = Coordinates {
+            latitude: 5,
+            longitude: 5,
+        };
+        assert!(transform.fits(pose("0 3 N"), top_right));
+        assert!(!transform.fits(pose("0 2 N"), top_right));
+        assert_eq!(transform.apply(pose("0 2 N"), top_right), None);
+        assert!(transform.fits(pose("2 0 W"), top_right));
+        assert!(!transform.fits(pose("2 0 E"), top_right));
+
+        assert_eq!(
+            net_transform("MMX"),
+            Err(RoverError::InvalidSyntax {
+                rover: 0,
+                offset: 2,
+                reason: "`X` isn't an instruction".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn counts_are_not_expanded() {
+        let transform = net_transform("(1000000M)1000").unwrap();
+        assert_eq!(transform.end.ahead, 1_000_000_000);
+        assert_eq!(transform.max.ahead, 1_000_000_000);
+        let top_right = Coordinates {
+            latitude: 5,
+            longitude: 5,
+        };
+        let transform = net_transform("(MMRML)18446744073709551615").unwrap();
+        assert_eq!(transform.apply(pose("0 0 N"), top_right), None);
+        // The same as the expanded instructions, whichever way the group turns the rover.
+        for source in [
+            "(MMR)7",
+            "3(BBLM)",
+            "(MUMRM)5",
+            "2(M(LBM)3)M",
+            "4(RMM)",
+            "(LLMR)9",
+        ] {
+            let program = Program::parse(source, 0, 1000, &Macros::default()).unwrap();
+            let expanded = NetTransform::of_steps(program.steps.chars());
+            assert_eq!(net_transform(source), expanded, "{source}");
+            let bytecode = Bytecode::compile(&program.steps, false);
+            assert_eq!(Ok(NetTransform::of(&bytecode.ops)), expanded, "{source}");
+        }
+    }
+}
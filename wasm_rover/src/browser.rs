@@ -0,0 +1,122 @@
+//! The wasm-bindgen interface, for running missions from javascript, e.g. to visualise them in the
+//! browser. Built with `wasm-pack build --target web -- --no-default-features --features browser`.
+//!
+//! This is the same as the WIT interface, except that errors are thrown, and the error kinds are
+//! the WIT case names as strings (e.g. "out-of-bounds").
+
+use rover::{RoverError, RoverPosition, RunOptions};
+use wasm_bindgen::prelude::*;
+
+/// Where a rover is, and which way it is facing.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Pose {
+    pub latitude: u16,
+    pub longitude: u16,
+    facing: String,
+}
+
+#[wasm_bindgen]
+impl Pose {
+    /// One of "N", "S", "E" or "W".
+    #[wasm_bindgen(getter)]
+    pub fn facing(&self) -> String {
+        self.facing.clone()
+    }
+}
+
+impl From<RoverPosition> for Pose {
+    fn from(val: RoverPosition) -> Self {
+        Pose {
+            latitude: val.current_location.latitude,
+            longitude: val.current_location.longitude,
+            facing: val.facing.into(),
+        }
+    }
+}
+
+/// Thrown by `run`.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct MissionError {
+    kind: &'static str,
+    message: String,
+}
+
+#[wasm_bindgen]
+impl MissionError {
+    /// The `error-kind` case from `wit/rover.wit`, e.g. "out-of-bounds".
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> String {
+        self.kind.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+}
+
+impl From<RoverError> for MissionError {
+    fn from(val: RoverError) -> Self {
+        let kind = match val {
+            RoverError::InvalidInput => "invalid-input",
+            RoverError::TokenCount { .. } => "token-count",
+            RoverError::InvalidInt { .. } => "invalid-int",
+            RoverError::DirectionLen { .. } => "direction-len",
+            RoverError::OutOfBoundsStartPosition { .. } => "out-of-bounds-start-position",
+            RoverError::OutOfBoundsError { .. } => "out-of-bounds",
+            RoverError::DirectionError { .. } => "invalid-direction",
+            RoverError::InstructionError { .. } => "invalid-instruction",
+            RoverError::TooManyRovers { .. } => "too-many-rovers",
+            RoverError::InstructionsTooLong { .. } => "instructions-too-long",
+            RoverError::PlateauTooLarge { .. } => "plateau-too-large",
+            RoverError::TooManySteps { .. } => "too-many-steps",
+            RoverError::Cancelled { .. } => "cancelled",
+            RoverError::OutOfEnergy { .. } => "out-of-energy",
+            RoverError::InvalidHeightmap { .. } => "invalid-heightmap",
+            RoverError::TooSteep { .. } => "too-steep",
+            RoverError::Unreachable { .. } => "unreachable",
+            RoverError::Deadlock { .. } => "deadlock",
+            RoverError::InvalidSyntax { .. } => "invalid-syntax",
+            RoverError::InvalidMacroDefinition { .. } => "invalid-macro-definition",
+            RoverError::MacroError { .. } => "macro-error",
+            RoverError::Crash { .. } => "crash",
+            RoverError::StepLimit { .. } => "step-limit",
+        };
+        MissionError {
+            kind,
+            message: val.to_string(),
+        }
+    }
+}
+
+/// The final pose of each rover, given the top right of the plateau (e.g. "5 5") and a position
+/// line and instructions line for each rover.
+#[wasm_bindgen]
+pub fn run(top_right: String, instructions: String) -> Result<Vec<Pose>, MissionError> {
+    let positions =
+        rover::return_positions_with_options(top_right, instructions, &RunOptions::default())?;
+    Ok(positions.into_iter().map(Pose::from).collect())
+}
+
+/// The semantic version of the module.
+#[wasm_bindgen]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn run_returns_poses_or_error() {
+        let res = run("5 5".to_string(), "1 2 N\nLMLMLMLMM".to_string()).unwrap();
+        assert_eq!((res[0].latitude, res[0].longitude), (1, 3));
+        assert_eq!(res[0].facing(), "N");
+
+        let err = run("5 5".to_string(), "1 2 N\nMMXM".to_string()).unwrap_err();
+        assert_eq!(err.kind(), "invalid-instruction");
+    }
+}
@@ -0,0 +1,115 @@
+//! The WIT interface, generated by wit-bindgen from `wit/rover.wit`.
+//!
+//! Built for `wasm32-wasip2`, this is a component which any component-model host can run. Built
+//! for `wasm32-unknown-unknown`, it is a core module with the same exports, lowered with the
+//! canonical ABI, for runtimes without component support, e.g. go through gravity.
+
+use crate::component::exports::rover::mission::mission::{
+    Direction, ErrorKind, Guest, MissionError, Pose,
+};
+use rover::{RoverError, RoverPosition, RunOptions};
+
+wit_bindgen::generate!({
+    world: "rover",
+    path: "wit",
+});
+
+struct Component;
+
+// The exported symbol names (e.g. `rover:mission/mission@0.1.0#run`) aren't valid in a native
+// shared library, so they are only exported from wasm. Natively, `Component` is just tested.
+#[cfg(target_arch = "wasm32")]
+export!(Component);
+
+impl From<rover::Direction> for Direction {
+    fn from(val: rover::Direction) -> Self {
+        match val {
+            rover::Direction::North => Direction::North,
+            rover::Direction::East => Direction::East,
+            rover::Direction::South => Direction::South,
+            rover::Direction::West => Direction::West,
+        }
+    }
+}
+
+impl From<RoverPosition> for Pose {
+    fn from(val: RoverPosition) -> Self {
+        Pose {
+            latitude: val.current_location.latitude,
+            longitude: val.current_location.longitude,
+            facing: val.facing.into(),
+        }
+    }
+}
+
+impl From<RoverError> for MissionError {
+    fn from(val: RoverError) -> Self {
+        let kind = match val {
+            RoverError::InvalidInput => ErrorKind::InvalidInput,
+            RoverError::TokenCount { .. } => ErrorKind::TokenCount,
+            RoverError::InvalidInt { .. } => ErrorKind::InvalidInt,
+            RoverError::DirectionLen { .. } => ErrorKind::DirectionLen,
+            RoverError::OutOfBoundsStartPosition { .. } => ErrorKind::OutOfBoundsStartPosition,
+            RoverError::OutOfBoundsError { .. } => ErrorKind::OutOfBounds,
+            RoverError::DirectionError { .. } => ErrorKind::InvalidDirection,
+            RoverError::InstructionError { .. } => ErrorKind::InvalidInstruction,
+            RoverError::TooManyRovers { .. } => ErrorKind::TooManyRovers,
+            RoverError::InstructionsTooLong { .. } => ErrorKind::InstructionsTooLong,
+            RoverError::PlateauTooLarge { .. } => ErrorKind::PlateauTooLarge,
+            RoverError::TooManySteps { .. } => ErrorKind::TooManySteps,
+            RoverError::Cancelled { .. } => ErrorKind::Cancelled,
+            RoverError::OutOfEnergy { .. } => ErrorKind::OutOfEnergy,
+            RoverError::InvalidHeightmap { .. } => ErrorKind::InvalidHeightmap,
+            RoverError::TooSteep { .. } => ErrorKind::TooSteep,
+            RoverError::Unreachable { .. } => ErrorKind::Unreachable,
+            RoverError::Deadlock { .. } => ErrorKind::Deadlock,
+            RoverError::InvalidSyntax { .. } => ErrorKind::InvalidSyntax,
+            RoverError::InvalidMacroDefinition { .. } => ErrorKind::InvalidMacroDefinition,
+            RoverError::MacroError { .. } => ErrorKind::MacroError,
+            RoverError::Crash { .. } => ErrorKind::Crash,
+            RoverError::StepLimit { .. } => ErrorKind::StepLimit,
+        };
+        MissionError {
+            kind,
+            message: val.to_string(),
+        }
+    }
+}
+
+impl Guest for Component {
+    fn run(top_right: String, instructions: String) -> Result<Vec<Pose>, MissionError> {
+        let positions =
+            rover::return_positions_with_options(top_right, instructions, &RunOptions::default())?;
+        Ok(positions.into_iter().map(Pose::from).collect())
+    }
+
+    fn version() -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn run_returns_poses() {
+        let res = Component::run(
+            "5 5".to_string(),
+            "1 2 N\nLMLMLMLMM\n3 3 E\nMMRMMRMRRM".to_string(),
+        )
+        .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!((res[0].latitude, res[0].longitude), (1, 3));
+        assert_eq!(res[0].facing, Direction::North);
+        assert_eq!((res[1].latitude, res[1].longitude), (5, 1));
+        assert_eq!(res[1].facing, Direction::East);
+    }
+
+    #[test]
+    fn run_returns_error_kind() {
+        let err = Component::run("5 5".to_string(), "1 2 N\nMMMMMMMM".to_string()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::OutOfBounds);
+        assert_eq!(err.message, "Instruction tried to send Rover too far \"N\"");
+    }
+}
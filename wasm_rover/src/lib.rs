@@ -0,0 +1,15 @@
+//! WebAssembly builds of the `rover` crate.
+//!
+//! The `c_rover` crate needs a shared library for each platform that the go code runs on. A wasm
+//! module is the same file everywhere, so a go program can embed it with a pure-go runtime instead.
+//! There are two interfaces, each behind a feature:
+//! - `component` (the default): the WIT interface in `wit/rover.wit`, for WASI hosts.
+//! - `browser`: a wasm-bindgen interface, for javascript.
+//!
+//! Both are thin conversions of the `rover` types, and don't depend on being compiled to wasm, so
+//! they are tested natively.
+
+#[cfg(feature = "browser")]
+mod browser;
+#[cfg(feature = "component")]
+mod component;